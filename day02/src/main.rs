@@ -1,45 +1,173 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::env;
 use std::fs::File;
 use std::io::prelude::*;
 use std::io::BufReader;
 
 const PUZZLEINPUT: &str = "input.txt";
+const DEFAULT_MOVES: usize = 3;
+const DEFAULT_LIMIT: usize = 10;
+const USAGE: &str = "Usage: day02 [-n <limit>] [<moves>]";
+
+type BoxResult<T> = Result<T, Box<dyn std::error::Error>>;
+
+fn main() -> BoxResult<()> {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    let (limit, moves) = match args.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+        [] => (DEFAULT_LIMIT, DEFAULT_MOVES),
+        ["-n", n] => (n.parse()?, DEFAULT_MOVES),
+        ["-n", n, moves] => (n.parse()?, moves.parse()?),
+        [moves] => (DEFAULT_LIMIT, moves.parse()?),
+        _ => return Err(USAGE.into()),
+    };
+    let game = Game::new(moves)?;
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
     let file = File::open(PUZZLEINPUT)?;
     let reader = BufReader::new(file);
 
-    let (score1, score2) = {
-        let mut score1 = 0;
-        let mut score2 = 0;
-        for line in reader.lines() {
-            let line = line?;
-            let (a, b) = if let [a, b] = line.split_ascii_whitespace().collect::<Vec<_>>()[..] {
-                (to_move(a)?, to_move(b)?)
-            } else {
-                return Err("Invalid line".into());
-            };
-            score1 += winner_score(a, b) + b + 1;
-            score2 += b * 3 + pick_move(a, b) + 1;
+    let mut tally = vec![0; game.moves * game.moves];
+    for line in reader.lines() {
+        let line = line?;
+        let (a, b) = if let [a, b] = line.split_ascii_whitespace().collect::<Vec<_>>()[..] {
+            (game.opponent_move(a)?, game.player_symbol(b)?)
+        } else {
+            return Err("Invalid line".into());
+        };
+        tally[a * game.moves + b] += 1;
+    }
+
+    let identity = (0..game.moves).collect::<Vec<_>>();
+    println!(
+        "Part 1: {}\nPart 2: {}",
+        game.total_score1(&tally, &identity),
+        game.total_score2(&tally, &identity)
+    );
+
+    // the best `limit` mappings seen so far, with the worst on top, ranked by both scores and
+    // then by earliest permutation
+    let mut ranking = BinaryHeap::new();
+    let mut mapping = identity;
+    loop {
+        let score1 = game.total_score1(&tally, &mapping);
+        let score2 = game.total_score2(&tally, &mapping);
+        let better = match ranking.peek() {
+            _ if ranking.len() < limit => true,
+            Some(Reverse((worst1, worst2, _))) => (score1, score2) > (*worst1, *worst2),
+            None => false,
+        };
+        if better {
+            ranking.push(Reverse((score1, score2, Reverse(mapping.clone()))));
+            if ranking.len() > limit {
+                ranking.pop();
+            }
         }
-        (score1, score2)
-    };
-    println!("Part 1: {}\nPart 2: {}", score1, score2);
+        if !next_permutation(&mut mapping) {
+            break;
+        }
+    }
+    println!(
+        "Mappings of {}:",
+        game.format_mapping(&(0..game.moves).collect::<Vec<_>>())
+    );
+    for Reverse((score1, score2, Reverse(mapping))) in ranking.into_sorted_vec() {
+        println!(
+            "{}: Part 1: {}, Part 2: {}",
+            game.format_mapping(&mapping),
+            score1,
+            score2
+        );
+    }
     Ok(())
 }
 
-fn pick_move(a: i32, b: i32) -> i32 {
-    (a + b + 2) % 3
+/// A generalized rock paper scissors game with an odd number of moves, where each move defeats
+/// the half of the other moves that precede it cyclically.
+struct Game {
+    moves: usize,
 }
 
-fn winner_score(a: i32, b: i32) -> i32 {
-    ((b - a + 3 + 1) % 3) * 3
-}
+impl Game {
+    fn new(moves: usize) -> BoxResult<Self> {
+        // opponent symbols start at A and player symbols end at Z, and must not overlap, and
+        // ranking every mapping of 13 moves would take hours
+        if moves < 3 || moves.is_multiple_of(2) || moves > 11 {
+            return Err("Invalid number of moves".into());
+        }
+        Ok(Self { moves })
+    }
+
+    fn opponent_move(&self, a: &str) -> BoxResult<usize> {
+        match a.as_bytes() {
+            &[c] if c >= b'A' && ((c - b'A') as usize) < self.moves => Ok((c - b'A') as usize),
+            _ => Err("Invalid move".into()),
+        }
+    }
+
+    fn player_symbol(&self, a: &str) -> BoxResult<usize> {
+        let first = b'Z' + 1 - self.moves as u8;
+        match a.as_bytes() {
+            &[c] if (first..=b'Z').contains(&c) => Ok((c - first) as usize),
+            _ => Err("Invalid move".into()),
+        }
+    }
+
+    fn format_mapping(&self, mapping: &[usize]) -> String {
+        let first = b'Z' + 1 - self.moves as u8;
+        mapping.iter().map(|&i| (first + i as u8) as char).collect()
+    }
+
+    /// Returns 0 for a loss, 1 for a draw, and 2 for a win for the player.
+    fn outcome(&self, a: usize, b: usize) -> usize {
+        let delta = (b + self.moves - a) % self.moves;
+        if delta == 0 {
+            1
+        } else if delta <= self.moves / 2 {
+            2
+        } else {
+            0
+        }
+    }
+
+    fn score(&self, a: usize, b: usize) -> usize {
+        self.outcome(a, b) * 3 + b + 1
+    }
+
+    fn pick_move(&self, a: usize, b: usize) -> usize {
+        (a + self.moves + b - self.moves / 2) % self.moves
+    }
+
+    fn total_score1(&self, tally: &[usize], mapping: &[usize]) -> usize {
+        self.total_score(tally, mapping, |a, b| self.score(a, b))
+    }
 
-fn to_move(a: &str) -> Result<i32, Box<dyn std::error::Error>> {
-    match a {
-        "A" | "X" => Ok(0),
-        "B" | "Y" => Ok(1),
-        "C" | "Z" => Ok(2),
-        _ => Err("Invalid move".into()),
+    fn total_score2(&self, tally: &[usize], mapping: &[usize]) -> usize {
+        self.total_score(tally, mapping, |a, b| self.score(a, self.pick_move(a, b)))
     }
+
+    fn total_score(
+        &self,
+        tally: &[usize],
+        mapping: &[usize],
+        score: impl Fn(usize, usize) -> usize,
+    ) -> usize {
+        let mut total = 0;
+        for a in 0..self.moves {
+            for (b, &m) in mapping.iter().enumerate() {
+                total += tally[a * self.moves + b] * score(a, m);
+            }
+        }
+        total
+    }
+}
+
+fn next_permutation(v: &mut [usize]) -> bool {
+    let i = match (1..v.len()).rev().find(|&i| v[i - 1] < v[i]) {
+        Some(i) => i - 1,
+        None => return false,
+    };
+    let j = (i + 1..v.len()).rev().find(|&j| v[j] > v[i]).unwrap();
+    v.swap(i, j);
+    v[i + 1..].reverse();
+    true
 }
@@ -1,47 +1,66 @@
-use std::collections::HashSet;
+use std::env;
+use std::fmt;
 use std::fs::File;
 use std::io::prelude::*;
 use std::io::BufReader;
 
 const PUZZLEINPUT: &str = "input.txt";
+const DEFAULT_GROUP_SIZE: usize = 3;
+
+type BoxResult<T> = Result<T, Box<dyn std::error::Error>>;
+
+fn main() -> BoxResult<()> {
+    let group_size = match env::args().nth(1) {
+        Some(v) => v.parse()?,
+        None => DEFAULT_GROUP_SIZE,
+    };
+    if group_size == 0 {
+        return Err("Invalid group size".into());
+    }
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
     let file = File::open(PUZZLEINPUT)?;
     let reader = BufReader::new(file);
 
     let (sum1, sum2) = {
         let mut sum1 = 0;
         let mut sum2 = 0;
-        let mut group_size = 0;
-        let mut group_common = Vec::new();
-        for line in reader.lines() {
+        let mut group_len = 0;
+        let mut group_common = ItemSet::ALL;
+        for (n, line) in reader.lines().enumerate() {
             let line = line?.into_bytes();
             if line.len() % 2 != 0 {
-                return Err("Invalid line format".into());
+                return Err(format!("Invalid line format for rucksack {}", n + 1).into());
             }
             let halflen = line.len() / 2;
-            if let Some(c) = find_common(&line[..halflen], &line[halflen..]) {
-                sum1 += prio(c)?;
-            } else {
-                return Err("None in common".into());
-            }
+            let first = ItemSet::from_items(&line[..halflen])?;
+            let second = ItemSet::from_items(&line[halflen..])?;
+            sum1 += first.intersect(second).single().map_err(|common| {
+                format!(
+                    "Rucksack {} has {} items in common: {}",
+                    n + 1,
+                    common.len(),
+                    common
+                )
+            })?;
 
-            if group_size < 2 {
-                if group_size == 0 {
-                    group_common = line;
-                } else {
-                    group_common = find_common_all(&group_common, &line);
-                }
-                group_size += 1;
+            group_common = group_common.intersect(first.union(second));
+            group_len += 1;
+            if group_len < group_size {
                 continue;
             }
-            if let Some(c) = find_common(&group_common, &line) {
-                sum2 += prio(c)?;
-            } else {
-                return Err("None in common".into());
-            }
-            group_common.clear();
-            group_size = 0;
+            sum2 += group_common.single().map_err(|common| {
+                format!(
+                    "Group {} has {} badges: {}",
+                    n / group_size + 1,
+                    common.len(),
+                    common
+                )
+            })?;
+            group_common = ItemSet::ALL;
+            group_len = 0;
+        }
+        if group_len != 0 {
+            return Err("Incomplete last group".into());
         }
         (sum1, sum2)
     };
@@ -49,33 +68,84 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
-fn find_common_all(a: &[u8], b: &[u8]) -> Vec<u8> {
-    let first = HashSet::<_>::from_iter(a.iter());
-    let mut common = Vec::new();
-    for &i in b {
-        if first.contains(&i) {
-            common.push(i);
+/// A set of items stored as a bitmask where bit `i` is the item with priority `i + 1`.
+#[derive(Clone, Copy)]
+struct ItemSet(u64);
+
+impl ItemSet {
+    const ALL: Self = Self((1 << 52) - 1);
+
+    fn from_items(items: &[u8]) -> BoxResult<Self> {
+        let mut set = 0;
+        for &i in items {
+            set |= 1 << (prio(i)? - 1);
+        }
+        Ok(Self(set))
+    }
+
+    fn intersect(self, other: Self) -> Self {
+        Self(self.0 & other.0)
+    }
+
+    fn union(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+
+    fn prios(self) -> impl Iterator<Item = u32> {
+        let mut set = self.0;
+        std::iter::from_fn(move || {
+            if set == 0 {
+                return None;
+            }
+            let i = set.trailing_zeros();
+            set &= set - 1;
+            Some(i + 1)
+        })
+    }
+
+    fn len(self) -> u32 {
+        self.0.count_ones()
+    }
+
+    /// Returns the priority of the only item in the set, or the set itself otherwise.
+    fn single(self) -> Result<u32, Self> {
+        if self.len() == 1 {
+            Ok(self.0.trailing_zeros() + 1)
+        } else {
+            Err(self)
         }
     }
-    common
 }
 
-fn find_common(a: &[u8], b: &[u8]) -> Option<u8> {
-    let first = HashSet::<_>::from_iter(a.iter());
-    for &i in b {
-        if first.contains(&i) {
-            return Some(i);
+impl fmt::Display for ItemSet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.0 == 0 {
+            return write!(f, "none");
+        }
+        for (n, i) in self.prios().enumerate() {
+            if n > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{} ({})", item(i) as char, i)?;
         }
+        Ok(())
     }
-    None
 }
 
-fn prio(c: u8) -> Result<i32, Box<dyn std::error::Error>> {
-    if c >= b'a' && c <= b'z' {
-        return Ok(c as i32 - 'a' as i32 + 1);
+fn prio(c: u8) -> BoxResult<u32> {
+    if c.is_ascii_lowercase() {
+        return Ok((c - b'a') as u32 + 1);
     }
-    if c >= b'A' && c <= b'Z' {
-        return Ok(c as i32 - 'A' as i32 + 27);
+    if c.is_ascii_uppercase() {
+        return Ok((c - b'A') as u32 + 27);
     }
     Err("Invalid prio".into())
 }
+
+fn item(prio: u32) -> u8 {
+    if prio <= 26 {
+        b'a' + (prio - 1) as u8
+    } else {
+        b'A' + (prio - 27) as u8
+    }
+}
@@ -1,6 +1,6 @@
+use intervalset::{Interval, IntervalSet};
 use lazy_static::lazy_static;
 use regex::Regex;
-use std::cmp::Ordering;
 use std::fs::File;
 use std::io::prelude::*;
 use std::io::BufReader;
@@ -13,99 +13,81 @@ fn main() -> BoxResult<()> {
     let file = File::open(PUZZLEINPUT)?;
     let reader = BufReader::new(file);
 
-    let (count1, count2) = {
+    let (count1, count2, max_overlap) = {
         let mut count1 = 0;
         let mut count2 = 0;
-        for line in reader.lines() {
-            let (a, b) = parse_line(&line?)?;
-            if is_fully_contained(&a, &b) {
+        let mut max_overlap = (0, 0);
+        for (n, line) in reader.lines().enumerate() {
+            let ranges = parse_line(&line?)?;
+            let sets = ranges
+                .iter()
+                .map(|&i| IntervalSet::from(i))
+                .collect::<Vec<_>>();
+            if has_contained(&sets) {
                 count1 += 1;
-            } else if is_overlap(&a, &b) {
+            }
+            let overlap = count_max_overlap(&ranges);
+            if overlap > 1 {
                 count2 += 1;
             }
+            if overlap > max_overlap.0 {
+                max_overlap = (overlap, n + 1);
+            }
         }
-        (count1, count2)
+        (count1, count2, max_overlap)
     };
-    println!("Part 1: {}\nPart 2: {}", count1, count1 + count2);
+    println!(
+        "Part 1: {}\nPart 2: {}\nMax overlap: {} (line {})",
+        count1, count2, max_overlap.0, max_overlap.1
+    );
     Ok(())
 }
 
-fn parse_line(line: &str) -> BoxResult<(Pair, Pair)> {
+fn parse_line(line: &str) -> BoxResult<Vec<Interval>> {
     lazy_static! {
-        static ref RE: Regex = Regex::new(r"^([0-9]+)-([0-9]+),([0-9]+)-([0-9]+)$").unwrap();
+        static ref RE: Regex = Regex::new(r"^([0-9]+)-([0-9]+)$").unwrap();
     }
-    let captures = RE.captures(line).ok_or("Invalid line")?;
-    Ok((
-        Pair::from_str(
-            captures.get(1).ok_or("Invalid line")?.as_str(),
-            captures.get(2).ok_or("Invalid line")?.as_str(),
-        )?,
-        Pair::from_str(
-            captures.get(3).ok_or("Invalid line")?.as_str(),
-            captures.get(4).ok_or("Invalid line")?.as_str(),
-        )?,
-    ))
+    line.split(',')
+        .map(|i| {
+            let captures = RE.captures(i).ok_or("Invalid line")?;
+            Ok(Interval::new(
+                captures.get(1).ok_or("Invalid line")?.as_str().parse()?,
+                captures.get(2).ok_or("Invalid line")?.as_str().parse()?,
+            ))
+        })
+        .collect()
 }
 
-struct Pair(i32, i32);
-
-impl Pair {
-    fn new(x: i32, y: i32) -> Self {
-        if x < y {
-            Self(x, y)
-        } else {
-            Self(y, x)
+fn has_contained(sets: &[IntervalSet]) -> bool {
+    for (i, a) in sets.iter().enumerate() {
+        for (j, b) in sets.iter().enumerate() {
+            if i != j && a.is_superset(b) {
+                return true;
+            }
         }
     }
-
-    fn from_str(x: &str, y: &str) -> BoxResult<Self> {
-        Ok(Self::new(x.parse::<i32>()?, y.parse::<i32>()?))
-    }
-}
-
-fn is_fully_contained(a: &Pair, b: &Pair) -> bool {
-    is_inclusive(a, b) || is_inclusive(b, a)
-}
-
-fn is_inclusive(a: &Pair, b: &Pair) -> bool {
-    b.0 >= a.0 && b.1 <= a.1
+    false
 }
 
-fn is_overlap(a: &Pair, b: &Pair) -> bool {
-    let mut intervals = vec![
-        Pair(a.0, 0),
-        Pair(a.1 + 1, 1),
-        Pair(b.0, 0),
-        Pair(b.1 + 1, 1),
-    ];
-    intervals.sort_unstable_by(|a, b| {
-        if a.0 < b.0 {
-            Ordering::Less
-        } else if a.0 > b.0 {
-            Ordering::Greater
-        } else {
-            if a.1 != b.1 {
-                if a.1 == 1 {
-                    Ordering::Less
-                } else {
-                    Ordering::Greater
-                }
-            } else {
-                Ordering::Equal
-            }
-        }
-    });
+fn count_max_overlap(ranges: &[Interval]) -> usize {
+    // ranges are closed, so a range ending at a position still overlaps one starting there:
+    // starts (false) sort before ends (true) at the same position
+    let mut events = Vec::with_capacity(ranges.len() * 2);
+    for i in ranges {
+        events.push((i.start(), false));
+        events.push((i.end(), true));
+    }
+    events.sort_unstable();
 
     let mut count = 0;
-    for Pair(_, y) in intervals {
-        if y == 0 {
-            count += 1;
-            if count > 1 {
-                return true;
-            }
-        } else {
+    let mut max_count = 0;
+    for (_, is_end) in events {
+        if is_end {
             count -= 1;
+        } else {
+            count += 1;
+            max_count = max_count.max(count);
         }
     }
-    false
+    max_count
 }
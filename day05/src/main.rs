@@ -1,5 +1,8 @@
 use lazy_static::lazy_static;
 use regex::Regex;
+use std::collections::HashSet;
+use std::env;
+use std::fmt;
 use std::fs::File;
 use std::io::prelude::*;
 use std::io::BufReader;
@@ -10,42 +13,168 @@ type BoxError = Box<dyn std::error::Error>;
 type BoxResult<T> = Result<T, BoxError>;
 
 fn main() -> BoxResult<()> {
+    let mut opts = Options::from_args(env::args().skip(1))?;
+
     let file = File::open(PUZZLEINPUT)?;
     let reader = BufReader::new(file);
 
-    let mut grid1 = Grid::empty();
-    let mut grid2 = Grid::empty();
+    let mut runs = vec![
+        Run::new("Part 1", Box::new(CrateMover9000)),
+        Run::new("Part 2", Box::new(CrateMover9001)),
+    ];
+    runs.append(&mut opts.cranes);
 
-    let mut rows = Vec::new();
+    let mut rows = Vec::<String>::new();
+    let mut initial = Grid::empty();
+    let mut instrs = Vec::new();
 
     let mut mode_grid = true;
 
     for line in reader.lines() {
         let line = line?;
         if mode_grid {
-            if line.len() == 0 {
-                grid1 = Grid::from_rows(&rows)?;
-                grid2 = grid1.clone();
+            if line.is_empty() {
+                let footer = rows.pop().ok_or("No rows")?;
+                let rows = rows
+                    .iter()
+                    .map(|i| parse_grid_row(i))
+                    .collect::<BoxResult<Vec<_>>>()?;
+                initial = Grid::from_rows(&rows, &parse_footer(&footer)?)?;
+                for i in &mut runs {
+                    i.grid = initial.clone();
+                }
+                if opts.trace.includes(0) {
+                    print!("== Initial\n{}\n", initial);
+                }
                 mode_grid = false;
                 continue;
             }
-            let row = parse_grid_row(line.as_bytes())?;
-            if row.len() != 0 {
-                rows.push(row);
-            }
+            rows.push(line);
             continue;
         }
         let instr = Instr::from_str(&line)?;
-        grid1.process_instr_1(&instr)?;
-        grid2.process_instr_2(&instr)?;
+        instrs.push(instr);
+        let step = instrs.len();
+        for i in &mut runs {
+            i.exec(&instrs[step - 1])
+                .map_err(|err| format!("Instruction {} ({}): {}", step, instrs[step - 1], err))?;
+            if opts.trace.includes(step) {
+                print!(
+                    "== {} after {}: {}\n{}\n",
+                    i.name,
+                    step,
+                    instrs[step - 1],
+                    i.grid
+                );
+            }
+        }
+    }
+    if mode_grid {
+        return Err("Missing blank line after the diagram".into());
+    }
+    if initial.grid.is_empty() {
+        return Err("Diagram has no stacks".into());
+    }
+    for i in &runs {
+        println!("{}: {}", i.name, format_labels(&i.grid.tops()?));
+    }
+
+    if let Some(step) = opts.rewind {
+        for i in &mut runs {
+            i.rewind(step)?;
+            let replayed = replay(&initial, i.crane.as_ref(), &instrs[..step])?;
+            if replayed != i.grid {
+                return Err(format!("{} rewind does not match replay at {}", i.name, step).into());
+            }
+            print!("== {} rewound to {}\n{}\n", i.name, step, i.grid);
+        }
     }
-    println!("Part 1: {}\nPart 2: {}", grid1.tops()?, grid2.tops()?);
     Ok(())
 }
 
-#[derive(Clone)]
+fn format_labels(labels: &[String]) -> String {
+    if labels.iter().all(|i| i.len() == 1) {
+        labels.concat()
+    } else {
+        labels.join(" ")
+    }
+}
+
+const USAGE: &str =
+    "Usage: day05 [trace[=steps]] [rewind=step] [crane=capacity:<n> | crane=rotation:<n>]...";
+
+struct Options {
+    trace: Trace,
+    rewind: Option<usize>,
+    /// Runs of the cranes configured in addition to the puzzle ones
+    cranes: Vec<Run>,
+}
+
+impl Options {
+    fn from_args(args: impl Iterator<Item = String>) -> BoxResult<Self> {
+        let mut opts = Self {
+            trace: Trace::Off,
+            rewind: None,
+            cranes: Vec::new(),
+        };
+        for arg in args {
+            match arg.split_once('=') {
+                None if arg == "trace" => opts.trace = Trace::All,
+                Some(("trace", steps)) => {
+                    opts.trace = Trace::Steps(
+                        steps
+                            .split(',')
+                            .map(|i| i.parse())
+                            .collect::<Result<_, _>>()?,
+                    )
+                }
+                Some(("rewind", step)) => opts.rewind = Some(step.parse()?),
+                Some(("crane", spec)) => opts.cranes.push(parse_crane(spec)?),
+                _ => return Err(USAGE.into()),
+            }
+        }
+        Ok(opts)
+    }
+}
+
+fn parse_crane(spec: &str) -> BoxResult<Run> {
+    let (kind, n) = spec.split_once(':').ok_or(USAGE)?;
+    let n = n.parse::<usize>()?;
+    match kind {
+        "capacity" if n > 0 => Ok(Run::new(
+            format!("Capacity {}", n),
+            Box::new(BoundedCrane { capacity: n }),
+        )),
+        "capacity" => Err("Crane has no capacity".into()),
+        "rotation" => Ok(Run::new(
+            format!("Rotation {}", n),
+            Box::new(RotatingCrane { rotation: n }),
+        )),
+        _ => Err(USAGE.into()),
+    }
+}
+
+/// Selects the instructions after which grids are printed, where instruction 0 is the initial
+/// grid.
+enum Trace {
+    Off,
+    All,
+    Steps(HashSet<usize>),
+}
+
+impl Trace {
+    fn includes(&self, step: usize) -> bool {
+        match self {
+            Self::Off => false,
+            Self::All => true,
+            Self::Steps(steps) => steps.contains(&step),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq)]
 struct Grid {
-    grid: Vec<Vec<u8>>,
+    grid: Vec<Vec<String>>,
 }
 
 impl Grid {
@@ -53,75 +182,276 @@ impl Grid {
         Self { grid: Vec::new() }
     }
 
-    fn from_rows(rows: &[Vec<u8>]) -> BoxResult<Self> {
-        let h = rows.len();
-        if h == 0 {
-            return Err("No rows".into());
+    /// Builds stacks from crate rows ordered top to bottom, assigning each crate to the stack
+    /// whose footer column it overlaps.
+    fn from_rows(rows: &[Vec<Crate>], footer: &[Span]) -> BoxResult<Self> {
+        if footer.is_empty() {
+            return Err("No stacks".into());
         }
-        let w = rows[0].len();
-        for i in rows {
-            if i.len() != w {
-                return Err("Mismatched rows".into());
+        let mut grid = vec![Vec::with_capacity(rows.len()); footer.len()];
+        for (n, row) in rows.iter().enumerate().rev() {
+            for i in row {
+                let stacks = footer
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, col)| col.overlaps(&i.span))
+                    .map(|(k, _)| k)
+                    .collect::<Vec<_>>();
+                let col = match stacks[..] {
+                    [col] => col,
+                    _ => {
+                        return Err(format!(
+                            "Crate [{}] in row {} is not aligned with a single stack",
+                            i.label,
+                            n + 1
+                        )
+                        .into())
+                    }
+                };
+                if grid[col].len() != rows.len() - n - 1 {
+                    return Err(format!(
+                        "Crate [{}] in row {} is not supported by a crate below",
+                        i.label,
+                        n + 1
+                    )
+                    .into());
+                }
+                grid[col].push(i.label.clone());
             }
         }
-        let mut grid = Vec::with_capacity(w);
-        for i in 0..w {
-            let mut col = Vec::with_capacity(h);
-            for j in 0..h {
-                let c = rows[h - j - 1][i];
-                if c == b'.' {
-                    break;
+        Ok(Self { grid })
+    }
+
+    /// Applies an instruction with a crane, leaving the grid unchanged if the instruction is
+    /// invalid.
+    fn exec(&mut self, crane: &dyn Crane, instr: &Instr) -> BoxResult<()> {
+        let &Instr(a, b, c) = instr;
+        if b >= self.grid.len() {
+            return Err(format!("No stack {}", b + 1).into());
+        }
+        if c >= self.grid.len() {
+            return Err(format!("No stack {}", c + 1).into());
+        }
+        if self.grid[b].len() < a {
+            return Err(format!("Stack {} has only {} crates", b + 1, self.grid[b].len()).into());
+        }
+        crane.apply(self, instr)
+    }
+
+    /// Removes the top `n` crates of a stack, returned in bottom to top order.
+    fn take(&mut self, col: usize, n: usize) -> BoxResult<Vec<String>> {
+        let stack = self.grid.get_mut(col).ok_or("No such stack")?;
+        if stack.len() < n {
+            return Err("No more items".into());
+        }
+        Ok(stack.split_off(stack.len() - n))
+    }
+
+    /// Places crates given in bottom to top order onto a stack.
+    fn put(&mut self, col: usize, crates: Vec<String>) -> BoxResult<()> {
+        self.grid
+            .get_mut(col)
+            .ok_or("No such stack")?
+            .extend(crates);
+        Ok(())
+    }
+
+    fn peek(&self, col: usize) -> BoxResult<&str> {
+        Ok(self.grid[col].last().ok_or("No more items")?)
+    }
+
+    fn tops(&self) -> BoxResult<Vec<String>> {
+        (0..self.grid.len())
+            .map(|i| Ok(self.peek(i)?.to_string()))
+            .collect()
+    }
+}
+
+/// Renders the grid in the puzzle's diagram format, widening columns to fit longer labels.
+impl fmt::Display for Grid {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let widths = self
+            .grid
+            .iter()
+            .enumerate()
+            .map(|(n, col)| {
+                col.iter()
+                    .map(|i| i.len() + 2)
+                    .chain([3, (n + 1).to_string().len()])
+                    .max()
+                    .unwrap_or(3)
+            })
+            .collect::<Vec<_>>();
+        let h = self.grid.iter().map(Vec::len).max().unwrap_or(0);
+        for y in (0..h).rev() {
+            for (n, col) in self.grid.iter().enumerate() {
+                if n > 0 {
+                    write!(f, " ")?;
+                }
+                match col.get(y) {
+                    Some(label) => write_centered(f, &format!("[{}]", label), widths[n])?,
+                    None => write_centered(f, "", widths[n])?,
                 }
-                col.push(c);
             }
-            grid.push(col);
+            writeln!(f)?;
         }
-        Ok(Self { grid })
+        for (n, &w) in widths.iter().enumerate() {
+            if n > 0 {
+                write!(f, " ")?;
+            }
+            write_centered(f, &(n + 1).to_string(), w)?;
+        }
+        writeln!(f)
     }
+}
+
+fn write_centered(f: &mut fmt::Formatter, s: &str, width: usize) -> fmt::Result {
+    let left = (width - s.len()) / 2;
+    write!(
+        f,
+        "{:left$}{}{:right$}",
+        "",
+        s,
+        "",
+        left = left,
+        right = width - s.len() - left
+    )
+}
+
+/// A crane applied to its own grid, recording enough history to undo each instruction.
+struct Run {
+    name: String,
+    crane: Box<dyn Crane>,
+    grid: Grid,
+    history: Vec<Undo>,
+}
+
+/// The crates that were on top of the source stack before an instruction moved them.
+struct Undo {
+    instr: Instr,
+    taken: Vec<String>,
+}
 
-    fn process_instr_1(&mut self, &Instr(a, b, c): &Instr) -> BoxResult<()> {
-        for _ in 0..a {
-            let k = self.pop(b)?;
-            self.push(c, k)
+impl Run {
+    fn new(name: impl Into<String>, crane: Box<dyn Crane>) -> Self {
+        Self {
+            name: name.into(),
+            crane,
+            grid: Grid::empty(),
+            history: Vec::new(),
         }
+    }
+
+    fn exec(&mut self, instr: &Instr) -> BoxResult<()> {
+        let &Instr(a, b, _) = instr;
+        let taken = match self.grid.grid.get(b) {
+            Some(stack) if stack.len() >= a => stack[stack.len() - a..].to_vec(),
+            _ => Vec::new(),
+        };
+        self.grid.exec(self.crane.as_ref(), instr)?;
+        self.history.push(Undo {
+            instr: *instr,
+            taken,
+        });
         Ok(())
     }
 
-    fn process_instr_2(&mut self, &Instr(a, b, c): &Instr) -> BoxResult<()> {
-        let mut stack = Vec::with_capacity(a);
-        for _ in 0..a {
-            stack.push(self.pop(b)?);
+    fn undo(&mut self) -> BoxResult<()> {
+        let Undo {
+            instr: Instr(a, b, c),
+            taken,
+        } = self.history.pop().ok_or("No instructions to undo")?;
+        self.grid.take(c, a)?;
+        self.grid.put(b, taken)
+    }
+
+    /// Undoes instructions until only the first `step` remain applied.
+    fn rewind(&mut self, step: usize) -> BoxResult<()> {
+        if step > self.history.len() {
+            return Err(format!("Cannot rewind forward to {}", step).into());
         }
-        for &i in stack.iter().rev() {
-            self.push(c, i)
+        while self.history.len() > step {
+            self.undo()?;
         }
         Ok(())
     }
+}
 
-    fn pop(&mut self, col: usize) -> BoxResult<u8> {
-        self.grid[col].pop().ok_or("No more items".into())
+fn replay(snapshot: &Grid, crane: &dyn Crane, instrs: &[Instr]) -> BoxResult<Grid> {
+    let mut grid = snapshot.clone();
+    for (n, i) in instrs.iter().enumerate() {
+        grid.exec(crane, i)
+            .map_err(|err| format!("Instruction {} ({}): {}", n + 1, i, err))?;
     }
+    Ok(grid)
+}
+
+trait Crane {
+    fn apply(&self, grid: &mut Grid, instr: &Instr) -> BoxResult<()>;
+}
 
-    fn push(&mut self, col: usize, b: u8) {
-        self.grid[col].push(b)
+/// Moves crates one at a time.
+struct CrateMover9000;
+
+impl Crane for CrateMover9000 {
+    fn apply(&self, grid: &mut Grid, &Instr(a, b, c): &Instr) -> BoxResult<()> {
+        let mut crates = grid.take(b, a)?;
+        crates.reverse();
+        grid.put(c, crates)?;
+        Ok(())
     }
+}
+
+/// Moves all crates at once, preserving their order.
+struct CrateMover9001;
+
+impl Crane for CrateMover9001 {
+    fn apply(&self, grid: &mut Grid, &Instr(a, b, c): &Instr) -> BoxResult<()> {
+        let crates = grid.take(b, a)?;
+        grid.put(c, crates)?;
+        Ok(())
+    }
+}
+
+/// Moves at most `capacity` crates at a time, preserving the order of each lift.
+struct BoundedCrane {
+    capacity: usize,
+}
 
-    fn peek(&self, col: usize) -> BoxResult<u8> {
-        Ok(self.grid[col]
-            .last()
-            .ok_or::<BoxError>("No more items".into())?
-            .clone())
+impl Crane for BoundedCrane {
+    fn apply(&self, grid: &mut Grid, &Instr(a, b, c): &Instr) -> BoxResult<()> {
+        if self.capacity == 0 {
+            return Err("Crane has no capacity".into());
+        }
+        let mut remaining = a;
+        while remaining > 0 {
+            let n = remaining.min(self.capacity);
+            let crates = grid.take(b, n)?;
+            grid.put(c, crates)?;
+            remaining -= n;
+        }
+        Ok(())
     }
+}
+
+/// Moves all crates at once, rotating the lifted stack so that its bottom `rotation` crates
+/// end up on top.
+struct RotatingCrane {
+    rotation: usize,
+}
 
-    fn tops(&self) -> BoxResult<String> {
-        let mut s = String::with_capacity(self.grid.len());
-        for i in 0..self.grid.len() {
-            s.push(self.peek(i)? as char);
+impl Crane for RotatingCrane {
+    fn apply(&self, grid: &mut Grid, &Instr(a, b, c): &Instr) -> BoxResult<()> {
+        let mut crates = grid.take(b, a)?;
+        if a > 0 {
+            crates.rotate_left(self.rotation % a);
         }
-        Ok(s)
+        grid.put(c, crates)?;
+        Ok(())
     }
 }
 
+#[derive(Clone, Copy)]
 struct Instr(usize, usize, usize);
 
 impl Instr {
@@ -132,39 +462,93 @@ impl Instr {
         let captures = RE.captures(line).ok_or("Invalid line")?;
         Ok(Self(
             captures.get(1).ok_or("Invalid line")?.as_str().parse()?,
-            captures
-                .get(2)
-                .ok_or("Invalid line")?
-                .as_str()
-                .parse::<usize>()?
-                - 1,
-            captures
-                .get(3)
-                .ok_or("Invalid line")?
-                .as_str()
-                .parse::<usize>()?
-                - 1,
+            parse_stack_num(captures.get(2).ok_or("Invalid line")?.as_str())?,
+            parse_stack_num(captures.get(3).ok_or("Invalid line")?.as_str())?,
         ))
     }
 }
 
-fn parse_grid_row(line: &[u8]) -> BoxResult<Vec<u8>> {
-    let mut row = Vec::with_capacity((line.len() / 4) + 1);
-    for part in line.chunks(4) {
-        if part.len() < 3 {
-            break;
-        }
-        match part {
-            &[b' ', v, ..] => {
-                if v != b' ' {
-                    return Ok(Vec::new());
-                } else {
-                    row.push(b'.');
+impl fmt::Display for Instr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "move {} from {} to {}", self.0, self.1 + 1, self.2 + 1)
+    }
+}
+
+fn parse_stack_num(s: &str) -> BoxResult<usize> {
+    s.parse::<usize>()?
+        .checked_sub(1)
+        .ok_or_else(|| "Invalid stack number".into())
+}
+
+/// A half open range of byte columns in a diagram line.
+#[derive(Clone, Copy)]
+struct Span {
+    start: usize,
+    end: usize,
+}
+
+impl Span {
+    fn overlaps(&self, other: &Self) -> bool {
+        self.start < other.end && other.start < self.end
+    }
+}
+
+struct Crate {
+    span: Span,
+    label: String,
+}
+
+fn parse_grid_row(line: &str) -> BoxResult<Vec<Crate>> {
+    let mut row = Vec::new();
+    let mut rest = line.char_indices();
+    while let Some((start, c)) = rest.next() {
+        match c {
+            ' ' => continue,
+            '[' => {
+                let end = rest
+                    .by_ref()
+                    .find(|&(_, c)| c == ']')
+                    .ok_or("Unclosed crate label")?
+                    .0;
+                let label = &line[start + 1..end];
+                if label.is_empty() {
+                    return Err("Empty crate label".into());
                 }
+                row.push(Crate {
+                    span: Span {
+                        start,
+                        end: end + 1,
+                    },
+                    label: label.to_string(),
+                });
             }
-            &[b'[', v, ..] => row.push(v),
             _ => return Err("Invalid line".into()),
         }
     }
     Ok(row)
 }
+
+/// Parses the numeric footer of a diagram, which must number the stacks from 1 in order.
+fn parse_footer(line: &str) -> BoxResult<Vec<Span>> {
+    let mut cols = Vec::new();
+    let mut start = None;
+    for (i, c) in line
+        .char_indices()
+        .chain(std::iter::once((line.len(), ' ')))
+    {
+        match (c, start) {
+            (' ', Some(k)) => {
+                if line[k..i].parse::<usize>()? != cols.len() + 1 {
+                    return Err("Stacks not numbered in order".into());
+                }
+                cols.push(Span { start: k, end: i });
+                start = None;
+            }
+            (' ', None) => {}
+            ('0'..='9', Some(_)) => {}
+            ('0'..='9', None) => start = Some(i),
+            _ => return Err("Invalid footer".into()),
+        }
+    }
+    Ok(cols)
+}
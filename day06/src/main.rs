@@ -1,79 +1,297 @@
-use std::collections::hash_map::Entry;
-use std::collections::HashMap;
+use std::env;
 use std::fs::File;
+use std::io;
 use std::io::prelude::*;
 use std::io::BufReader;
 
 const PUZZLEINPUT: &str = "input.txt";
+const DEFAULT_WINDOWS: [usize; 2] = [4, 14];
 
 type BoxError = Box<dyn std::error::Error>;
 type BoxResult<T> = Result<T, BoxError>;
 
 fn main() -> BoxResult<()> {
-    let file = File::open(PUZZLEINPUT)?;
-    let reader = BufReader::new(file);
-    let mut file = Vec::new();
-    for b in reader.bytes() {
-        file.push(b?);
+    let mut args = env::args().skip(1).peekable();
+    let reader: Box<dyn Read> = if args.peek().map(String::as_str) == Some("-") {
+        args.next();
+        Box::new(io::stdin().lock())
+    } else {
+        Box::new(File::open(PUZZLEINPUT)?)
+    };
+    let reader = BufReader::new(reader);
+
+    match args.peek().map(String::as_str) {
+        Some("all") => {
+            args.next();
+            let size = parse_size(args.next())?;
+            for pos in Markers::new(reader, size) {
+                println!("{}", pos?);
+            }
+        }
+        Some("runs") => {
+            args.next();
+            let min_len = parse_size(args.next())?;
+            for run in UniqueRuns::new(reader, min_len) {
+                let (start, end) = run?;
+                println!("{}-{}: {}", start + 1, end, end - start);
+            }
+        }
+        _ => {
+            let args = args.map(|i| i.parse()).collect::<Result<Vec<usize>, _>>()?;
+            let custom = !args.is_empty();
+            let sizes = if custom {
+                args
+            } else {
+                DEFAULT_WINDOWS.to_vec()
+            };
+
+            let mut detector = MarkerDetector::new(&sizes)?;
+            for b in reader.bytes() {
+                let b = b?;
+                if is_line_end(b) {
+                    break;
+                }
+                detector.push(b);
+                if detector.is_done() {
+                    break;
+                }
+            }
+
+            for (n, w) in detector.windows.iter().enumerate() {
+                let marker = w
+                    .marker
+                    .ok_or_else(|| format!("Failed to find marker of size {}", w.size))?;
+                if custom {
+                    println!("Window {}: {}", w.size, marker);
+                } else {
+                    println!("Part {}: {}", n + 1, marker);
+                }
+            }
+        }
     }
+    Ok(())
+}
 
-    let mut startpos1 = None;
-    let mut startpos2 = None;
+fn parse_size(arg: Option<String>) -> BoxResult<usize> {
+    match arg.map(|i| i.parse()) {
+        Some(Ok(size)) if size > 0 => Ok(size),
+        _ => Err("Usage: day06 [-] [all <size> | runs <min_len> | <sizes>...]".into()),
+    }
+}
 
-    let mut seen1 = HashMap::new();
-    let mut seen2 = HashMap::new();
+/// Returns whether a byte ends the line holding the signal.
+fn is_line_end(b: u8) -> bool {
+    b == b'\n' || b == b'\r'
+}
 
-    for i in 0..file.len() {
-        if startpos1.is_some() && startpos2.is_some() {
-            break;
+/// Finds the end of the first window of each size whose bytes are all distinct.
+struct MarkerDetector {
+    windows: Vec<Window>,
+    history: Vec<u8>,
+    pos: usize,
+}
+
+struct Window {
+    size: usize,
+    counts: [usize; 256],
+    /// Number of byte values that occur more than once in the window
+    dups: usize,
+    marker: Option<usize>,
+}
+
+impl MarkerDetector {
+    fn new(sizes: &[usize]) -> BoxResult<Self> {
+        if sizes.contains(&0) {
+            return Err("Invalid window size".into());
         }
+        Ok(Self {
+            windows: sizes
+                .iter()
+                .map(|&size| Window {
+                    size,
+                    counts: [0; 256],
+                    dups: 0,
+                    marker: None,
+                })
+                .collect(),
+            history: vec![0; sizes.iter().copied().max().unwrap_or(0)],
+            pos: 0,
+        })
+    }
 
-        let c = file[i];
+    fn is_done(&self) -> bool {
+        self.windows.iter().all(|i| i.marker.is_some())
+    }
 
-        if startpos1.is_none() {
-            let v = seen1.entry(c).or_insert(0);
-            *v += 1;
-            if i > 3 {
-                if let Entry::Occupied(mut e) = seen1.entry(file[i - 4]) {
-                    let v = e.get_mut();
-                    if *v < 2 {
-                        e.remove_entry();
-                    } else {
-                        *v -= 1;
-                    }
+    fn push(&mut self, c: u8) {
+        let cap = self.history.len();
+        for w in &mut self.windows {
+            if w.marker.is_some() {
+                continue;
+            }
+            if self.pos >= w.size {
+                let out = self.history[(self.pos - w.size) % cap] as usize;
+                w.counts[out] -= 1;
+                if w.counts[out] == 1 {
+                    w.dups -= 1;
                 }
             }
-            if i >= 3 && is_uniq(&seen1) {
-                startpos1 = Some(i + 1);
+            w.counts[c as usize] += 1;
+            if w.counts[c as usize] == 2 {
+                w.dups += 1;
             }
+            if self.pos + 1 >= w.size && w.dups == 0 {
+                w.marker = Some(self.pos + 1);
+            }
+        }
+        if cap > 0 {
+            self.history[self.pos % cap] = c;
         }
+        self.pos += 1;
+    }
+}
 
-        if startpos2.is_none() {
-            let v = seen2.entry(c).or_insert(0);
-            *v += 1;
-            if i > 13 {
-                if let Entry::Occupied(mut e) = seen2.entry(file[i - 14]) {
-                    let v = e.get_mut();
-                    if *v < 2 {
-                        e.remove_entry();
-                    } else {
-                        *v -= 1;
-                    }
-                }
+/// Tracks the longest run of distinct bytes ending at the most recent byte.
+struct RunTracker {
+    /// One past the index of the last occurrence of each byte value, or 0 if unseen
+    last_seen: [usize; 256],
+    start: usize,
+    pos: usize,
+}
+
+impl RunTracker {
+    fn new() -> Self {
+        Self {
+            last_seen: [0; 256],
+            start: 0,
+            pos: 0,
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.pos - self.start
+    }
+
+    fn push(&mut self, c: u8) {
+        let last = &mut self.last_seen[c as usize];
+        if *last > self.start {
+            self.start = *last;
+        }
+        self.pos += 1;
+        *last = self.pos;
+    }
+}
+
+/// Yields the end position of every window of `size` distinct bytes on the first line.
+struct Markers<R> {
+    bytes: io::Bytes<R>,
+    runs: RunTracker,
+    size: usize,
+    done: bool,
+}
+
+impl<R: BufRead> Markers<R> {
+    fn new(reader: R, size: usize) -> Self {
+        Self {
+            bytes: reader.bytes(),
+            runs: RunTracker::new(),
+            size,
+            done: false,
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for Markers<R> {
+    type Item = io::Result<usize>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        for b in self.bytes.by_ref() {
+            match b {
+                Ok(b) if is_line_end(b) => break,
+                Ok(b) => self.runs.push(b),
+                Err(err) => return Some(Err(err)),
             }
-            if i >= 13 && is_uniq(&seen2) {
-                startpos2 = Some(i + 1);
+            if self.runs.len() >= self.size {
+                return Some(Ok(self.runs.pos));
             }
         }
+        self.done = true;
+        None
     }
-    println!(
-        "Part 1: {}\nPart 2: {}",
-        startpos1.ok_or("Failed to find start 1")?,
-        startpos2.ok_or("Failed to find start 2")?,
-    );
-    Ok(())
 }
 
-fn is_uniq(seen: &HashMap<u8, usize>) -> bool {
-    seen.values().find(|&&v| v > 1).is_none()
+/// Yields the half open byte ranges of every maximal run of distinct bytes that is at least
+/// `min_len` long on the first line.
+struct UniqueRuns<R> {
+    bytes: io::Bytes<R>,
+    runs: RunTracker,
+    min_len: usize,
+    done: bool,
+}
+
+impl<R: BufRead> UniqueRuns<R> {
+    fn new(reader: R, min_len: usize) -> Self {
+        Self {
+            bytes: reader.bytes(),
+            runs: RunTracker::new(),
+            min_len,
+            done: false,
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for UniqueRuns<R> {
+    type Item = io::Result<(usize, usize)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        for b in self.bytes.by_ref() {
+            let b = match b {
+                Ok(b) if is_line_end(b) => break,
+                Ok(b) => b,
+                Err(err) => return Some(Err(err)),
+            };
+            let (start, end) = (self.runs.start, self.runs.pos);
+            self.runs.push(b);
+            if self.runs.start > start && end - start >= self.min_len {
+                return Some(Ok((start, end)));
+            }
+        }
+        self.done = true;
+        if self.runs.len() >= self.min_len {
+            Some(Ok((self.runs.start, self.runs.pos)))
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn markers_stop_at_line_end() {
+        for input in [&b"abcabcd\n"[..], b"abcabcd\r\nefgh\n"] {
+            let markers = Markers::new(input, 4)
+                .collect::<io::Result<Vec<_>>>()
+                .unwrap();
+            assert_eq!(markers, [7]);
+        }
+    }
+
+    #[test]
+    fn unique_runs_stop_at_line_end() {
+        for input in [&b"abcabcd\n"[..], b"abcabcd\r\nefgh\n"] {
+            let runs = UniqueRuns::new(input, 3)
+                .collect::<io::Result<Vec<_>>>()
+                .unwrap();
+            assert_eq!(runs, [(0, 3), (1, 4), (2, 5), (3, 7)]);
+        }
+    }
 }
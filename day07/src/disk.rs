@@ -0,0 +1,92 @@
+use super::{add_size, BoxResult, Node};
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+/// Creates the directories and sparse files under a node inside `dir`, which must be empty or
+/// not exist yet.
+pub fn materialize(node: &Node, dir: &Path) -> BoxResult<()> {
+    if dir.exists() && fs::read_dir(dir)?.next().is_some() {
+        return Err(format!("{} is not empty", dir.display()).into());
+    }
+    fs::create_dir_all(dir)?;
+    write_children(node, dir)
+}
+
+fn write_children(node: &Node, dir: &Path) -> BoxResult<()> {
+    for (k, v) in &node.children {
+        let path = dir.join(k);
+        if v.is_dir {
+            fs::create_dir(&path)?;
+            write_children(v, &path)?;
+        } else {
+            fs::File::create(&path)?.set_len(v.size)?;
+        }
+    }
+    Ok(())
+}
+
+/// Writes a `$ cd` and `$ ls` transcript of a real directory tree rooted at `dir`.
+pub fn write_transcript(dir: &Path, out: &mut impl Write) -> BoxResult<()> {
+    writeln!(out, "$ cd /")?;
+    write_dir_transcript(dir, out)
+}
+
+fn write_dir_transcript(dir: &Path, out: &mut impl Write) -> BoxResult<()> {
+    let entries = read_entries(dir)?;
+    writeln!(out, "$ ls")?;
+    for (name, size) in &entries {
+        match size {
+            Some(size) => writeln!(out, "{} {}", size, name)?,
+            None => writeln!(out, "dir {}", name)?,
+        }
+    }
+    for (name, size) in &entries {
+        if size.is_none() {
+            writeln!(out, "$ cd {}", name)?;
+            write_dir_transcript(&dir.join(name), out)?;
+            writeln!(out, "$ cd ..")?;
+        }
+    }
+    Ok(())
+}
+
+/// Returns the sorted names of the files and directories in `dir`, with the size of each file.
+fn read_entries(dir: &Path) -> BoxResult<Vec<(String, Option<u64>)>> {
+    let mut entries = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry
+            .file_name()
+            .into_string()
+            .map_err(|name| format!("Invalid file name {:?}", name))?;
+        if name.contains(char::is_whitespace) {
+            return Err(format!("Unsupported file name {:?}", name).into());
+        }
+        let kind = entry.file_type()?;
+        if kind.is_dir() {
+            entries.push((name, None));
+        } else if kind.is_file() {
+            entries.push((name, Some(entry.metadata()?.len())));
+        }
+    }
+    entries.sort();
+    Ok(entries)
+}
+
+/// Returns the apparent size of a file, or the total apparent size of the files under a
+/// directory.
+pub fn apparent_size(path: &Path) -> BoxResult<u64> {
+    let meta = fs::symlink_metadata(path)?;
+    if meta.is_dir() {
+        let mut total = 0;
+        for entry in fs::read_dir(path)? {
+            total = add_size(total, apparent_size(&entry?.path())?)?;
+        }
+        Ok(total)
+    } else if meta.is_file() {
+        Ok(meta.len())
+    } else {
+        Ok(0)
+    }
+}
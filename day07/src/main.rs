@@ -1,10 +1,18 @@
 use lazy_static::lazy_static;
 use regex::Regex;
-use std::collections::hash_map::Entry;
-use std::collections::HashMap;
+use std::cmp::Ordering;
+use std::collections::btree_map::Entry;
+use std::collections::{BTreeMap, BTreeSet};
+use std::env;
+use std::fmt;
+use std::fmt::Write;
 use std::fs::File;
+use std::io;
 use std::io::prelude::*;
 use std::io::BufReader;
+use std::path::Path;
+
+mod disk;
 
 const PUZZLEINPUT: &str = "input.txt";
 
@@ -20,142 +28,599 @@ fn main() -> BoxResult<()> {
     for line in reader.lines() {
         term.read_input(&line?)?;
     }
+    term.finish();
+    for i in &term.warnings {
+        eprintln!("warning: {}", i);
+    }
+
+    let fs = &term.fs;
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    match args.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+        [] => println!(
+            "Part 1: {}\nPart 2: {}",
+            fs.small_dir_size(SMALL_DIR_LIMIT)?,
+            fs.find_dir_to_free(TOTAL_DISK, MIN_UNUSED)?.unwrap_or(0)
+        ),
+        ["stat", path] => {
+            let stat = fs.stat(path)?;
+            println!(
+                "{}: {} {}",
+                stat.path,
+                if stat.is_dir { "dir" } else { "file" },
+                stat.size
+            );
+        }
+        ["du", path] | ["du", "-s", path] => println!("{}\t{}", fs.stat(path)?.size, path),
+        ["find", path, ref preds @ ..] => {
+            let query = FindQuery::from_args(preds)?;
+            for i in fs.find(path, &query)? {
+                println!("{}", i.path);
+            }
+        }
+        ["tree", path] => print!("{}", fs.tree(path, false)?),
+        ["tree", "-s", path] => print!("{}", fs.tree(path, true)?),
+        ["json", path] => println!("{}", fs.json(path)?),
+        ["materialize", dir] => disk::materialize(&fs.root, Path::new(dir))?,
+        ["transcript", dir] => disk::write_transcript(Path::new(dir), &mut io::stdout().lock())?,
+        ["check", dir] => check_disk(fs, Path::new(dir))?,
+        ["largest", n] => {
+            for i in fs.largest_dirs(n.parse()?)? {
+                println!("{}\t{}", i.size, i.path);
+            }
+        }
+        ["small", limit] => println!("{}", fs.small_dir_size(limit.parse()?)?),
+        ["free", total_disk, min_unused] => println!(
+            "{}",
+            fs.find_dir_to_free(total_disk.parse()?, min_unused.parse()?)?
+                .unwrap_or(0)
+        ),
+        _ => return Err(USAGE.into()),
+    }
+
+    Ok(())
+}
+
+const USAGE: &str = "Usage: day07 [stat <path> | du [-s] <path> | find <path> [-type d|f] [-name <glob>] [-size [+|-]<size>] | tree [-s] <path> | json <path> | materialize <dir> | transcript <dir> | check <dir> | largest <n> | small <limit> | free <total_disk> <min_unused>]";
 
-    let (total_size, small_dirs) = calc_small_dir_size(&mut term.root);
+/// Materializes the filesystem under `dir` and verifies that the directory sizes on disk, and
+/// those of a transcript read back from disk, match the sizes in memory.
+fn check_disk(fs: &Fs, dir: &Path) -> BoxResult<()> {
+    disk::materialize(&fs.root, dir)?;
 
-    let mut freed = 0;
-    let target = MIN_UNUSED + total_size - TOTAL_DISK;
-    if target > 0 {
-        if let Some(k) = find_dir_size_target(&term.root, target) {
-            freed = k;
+    let mut mismatches = 0;
+    let mut small_on_disk = 0;
+    for i in fs.dirs()? {
+        let size = disk::apparent_size(&dir.join(i.path.trim_start_matches('/')))?;
+        if i.size != size {
+            eprintln!("{}: {} in memory, {} on disk", i.path, i.size, size);
+            mismatches += 1;
+        }
+        if size <= SMALL_DIR_LIMIT {
+            small_on_disk = add_size(small_on_disk, size)?;
         }
     }
 
-    println!("Part 1: {}\nPart 2: {}", small_dirs, freed);
+    let mut transcript = Vec::new();
+    disk::write_transcript(dir, &mut transcript)?;
+    let mut reread = Term::new();
+    for line in transcript.lines() {
+        reread.read_input(&line?)?;
+    }
+    reread.finish();
 
+    let small = fs.small_dir_size(SMALL_DIR_LIMIT)?;
+    let small_reread = reread.fs.small_dir_size(SMALL_DIR_LIMIT)?;
+    println!(
+        "Small dir size: {} in memory, {} on disk, {} from transcript",
+        small, small_on_disk, small_reread
+    );
+    if mismatches > 0
+        || small != small_on_disk
+        || small != small_reread
+        || fs.json("/")? != reread.fs.json("/")?
+    {
+        return Err("Filesystem on disk does not match".into());
+    }
     Ok(())
 }
 
-const TOTAL_DISK: i32 = 70000000;
-const MIN_UNUSED: i32 = 30000000;
+const SMALL_DIR_LIMIT: u64 = 100000;
+const TOTAL_DISK: u64 = 70000000;
+const MIN_UNUSED: u64 = 30000000;
 
-fn find_dir_size_target(n: &Node, target: i32) -> Option<i32> {
-    if n.size < target {
-        return None;
+/// An in-memory filesystem rooted at `/`.
+struct Fs {
+    root: Box<Node>,
+}
+
+/// A file or directory with its total size.
+struct Stat {
+    path: String,
+    name: String,
+    is_dir: bool,
+    size: u64,
+}
+
+impl Fs {
+    fn new() -> Self {
+        Self {
+            root: Node::new_dir(),
+        }
+    }
+
+    fn lookup(&self, path: &[String]) -> Option<&Node> {
+        let mut node = &self.root;
+        for i in path {
+            node = node.children.get(i)?;
+        }
+        Some(node)
+    }
+
+    fn lookup_mut(&mut self, path: &[String]) -> Option<&mut Node> {
+        let mut node = &mut self.root;
+        for i in path {
+            node = node.children.get_mut(i)?;
+        }
+        Some(node)
+    }
+
+    fn resolve(&self, path: &str) -> BoxResult<(Vec<String>, &Node)> {
+        if !path.starts_with('/') {
+            return Err(format!("Path {} is not absolute", path).into());
+        }
+        let path = resolve_path(&[], path)?;
+        let node = self
+            .lookup(&path)
+            .ok_or_else(|| format!("No such file or directory: {}", format_path(&path)))?;
+        Ok((path, node))
+    }
+
+    /// Returns the stats of every entry under an absolute path, including itself, with
+    /// children listed before their parents.
+    fn walk(&self, path: &str) -> BoxResult<Vec<Stat>> {
+        let (path, node) = self.resolve(path)?;
+        let mut stats = Vec::new();
+        node.walk(&mut path.clone(), &mut stats)?;
+        Ok(stats)
+    }
+
+    fn stat(&self, path: &str) -> BoxResult<Stat> {
+        Ok(self.walk(path)?.pop().ok_or("Invariant violated")?)
+    }
+
+    /// Renders the entries under a path in the puzzle's tree notation, optionally with the
+    /// total size of each directory.
+    fn tree(&self, path: &str, dir_sizes: bool) -> BoxResult<String> {
+        let (path, node) = self.resolve(path)?;
+        let mut out = String::new();
+        node.write_tree(
+            path.last().map_or("/", String::as_str),
+            0,
+            dir_sizes,
+            &mut out,
+        )?;
+        Ok(out)
+    }
+
+    fn json(&self, path: &str) -> BoxResult<String> {
+        let (path, node) = self.resolve(path)?;
+        let mut out = String::new();
+        node.write_json(path.last().map_or("/", String::as_str), &mut out)?;
+        Ok(out)
+    }
+
+    fn dirs(&self) -> BoxResult<Vec<Stat>> {
+        let mut stats = Vec::new();
+        self.root.walk(&mut Vec::new(), &mut stats)?;
+        stats.retain(|i| i.is_dir);
+        Ok(stats)
+    }
+
+    fn find(&self, path: &str, query: &FindQuery) -> BoxResult<Vec<Stat>> {
+        let mut stats = self.walk(path)?;
+        stats.retain(|i| query.matches(i));
+        stats.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(stats)
+    }
+
+    fn largest_dirs(&self, n: usize) -> BoxResult<Vec<Stat>> {
+        let mut dirs = self.dirs()?;
+        dirs.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.path.cmp(&b.path)));
+        dirs.truncate(n);
+        Ok(dirs)
+    }
+
+    /// Sums the sizes of all directories whose size is at most `limit`.
+    fn small_dir_size(&self, limit: u64) -> BoxResult<u64> {
+        self.dirs()?
+            .iter()
+            .filter(|i| i.size <= limit)
+            .try_fold(0, |acc, i| add_size(acc, i.size))
+    }
+
+    /// Returns the size of the smallest directory that leaves at least `min_unused` space free
+    /// on a disk of size `total_disk` when deleted, or `None` if enough space is already free.
+    fn find_dir_to_free(&self, total_disk: u64, min_unused: u64) -> BoxResult<Option<u64>> {
+        let target = add_size(min_unused, self.root.total_size()?)?.saturating_sub(total_disk);
+        if target == 0 {
+            return Ok(None);
+        }
+        Ok(Some(
+            self.dirs()?
+                .iter()
+                .filter(|i| i.size >= target)
+                .map(|i| i.size)
+                .min()
+                .ok_or("No directory frees enough space")?,
+        ))
+    }
+}
+
+struct FindQuery {
+    is_dir: Option<bool>,
+    name: Option<String>,
+    size: Option<(Ordering, u64)>,
+}
+
+impl FindQuery {
+    fn from_args(args: &[&str]) -> BoxResult<Self> {
+        let mut query = Self {
+            is_dir: None,
+            name: None,
+            size: None,
+        };
+        for pred in args.chunks(2) {
+            match pred {
+                ["-type", "d"] => query.is_dir = Some(true),
+                ["-type", "f"] => query.is_dir = Some(false),
+                ["-name", glob] => query.name = Some(glob.to_string()),
+                ["-size", size] => {
+                    let (ord, size) = if let Some(size) = size.strip_prefix('+') {
+                        (Ordering::Greater, size)
+                    } else if let Some(size) = size.strip_prefix('-') {
+                        (Ordering::Less, size)
+                    } else {
+                        (Ordering::Equal, *size)
+                    };
+                    if size.is_empty() {
+                        return Err(USAGE.into());
+                    }
+                    query.size = Some((ord, size.parse()?));
+                }
+                _ => return Err(USAGE.into()),
+            }
+        }
+        Ok(query)
     }
-    let mut at_most = n.size;
-    for v in n.children.values() {
-        if !v.is_dir {
-            continue;
+
+    fn matches(&self, stat: &Stat) -> bool {
+        if let Some(is_dir) = self.is_dir {
+            if stat.is_dir != is_dir {
+                return false;
+            }
+        }
+        if let Some(name) = &self.name {
+            if !glob_match(name.as_bytes(), stat.name.as_bytes()) {
+                return false;
+            }
         }
-        if let Some(k) = find_dir_size_target(v, target) {
-            if k < at_most {
-                at_most = k;
+        if let Some((ord, size)) = self.size {
+            if stat.size.cmp(&size) != ord {
+                return false;
             }
         }
+        true
     }
-    Some(at_most)
 }
 
-const SMALL_DIR_LIMIT: i32 = 100000;
+fn glob_match(pattern: &[u8], name: &[u8]) -> bool {
+    match (pattern.first(), name.first()) {
+        (None, None) => true,
+        (Some(b'*'), _) => {
+            glob_match(&pattern[1..], name) || (!name.is_empty() && glob_match(pattern, &name[1..]))
+        }
+        (Some(b'?'), Some(_)) => glob_match(&pattern[1..], &name[1..]),
+        (Some(p), Some(n)) if p == n => glob_match(&pattern[1..], &name[1..]),
+        _ => false,
+    }
+}
 
-fn calc_small_dir_size(n: &mut Node) -> (i32, i32) {
-    let mut total = 0;
-    let mut cummulative = 0;
-    for v in n.children.values_mut() {
-        if v.is_dir {
-            let (t, c) = calc_small_dir_size(v);
-            total += t;
-            cummulative += c;
-        } else {
-            total += v.size;
+/// Resolves a path relative to `pwd` into its components, handling `.` and `..`.
+fn resolve_path(pwd: &[String], path: &str) -> BoxResult<Vec<String>> {
+    let mut res = if path.starts_with('/') {
+        Vec::new()
+    } else {
+        pwd.to_vec()
+    };
+    for i in path.split('/') {
+        match i {
+            "" | "." => {}
+            ".." => {
+                res.pop().ok_or("No parent directory from root")?;
+            }
+            name => res.push(name.into()),
         }
     }
-    n.size = total;
-    if total <= SMALL_DIR_LIMIT {
-        cummulative += total;
+    Ok(res)
+}
+
+fn add_size(a: u64, b: u64) -> BoxResult<u64> {
+    a.checked_add(b).ok_or_else(|| "Size overflow".into())
+}
+
+/// Parses a file size, which must consist only of decimal digits.
+fn parse_size(s: &str) -> Option<u64> {
+    if s.is_empty() || !s.bytes().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    s.parse().ok()
+}
+
+fn write_json_str(s: &str, out: &mut String) -> fmt::Result {
+    write!(out, "\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => write!(out, "\\\"")?,
+            '\\' => write!(out, "\\\\")?,
+            '\n' => write!(out, "\\n")?,
+            '\r' => write!(out, "\\r")?,
+            '\t' => write!(out, "\\t")?,
+            c if (c as u32) < 0x20 => write!(out, "\\u{:04x}", c as u32)?,
+            c => write!(out, "{}", c)?,
+        }
+    }
+    write!(out, "\"")
+}
+
+fn format_path(path: &[String]) -> String {
+    if path.is_empty() {
+        "/".into()
+    } else {
+        path.iter().map(|i| format!("/{}", i)).collect()
     }
-    (total, cummulative)
 }
 
 struct Node {
-    children: HashMap<String, Box<Node>>,
+    children: BTreeMap<String, Box<Node>>,
     is_dir: bool,
-    size: i32,
+    size: u64,
 }
 
 struct Term {
     pwd: Vec<String>,
-    root: Box<Node>,
-    running: Option<String>,
+    fs: Fs,
+    running: Option<Listing>,
+    line: usize,
+    warnings: Vec<String>,
+}
+
+/// An `ls` in progress, with the names listed so far.
+struct Listing {
+    path: Vec<String>,
+    /// Line of the `ls` command
+    line: usize,
+    seen: BTreeSet<String>,
 }
 
 impl Node {
     fn new_dir() -> Box<Self> {
         Box::new(Self {
-            children: HashMap::new(),
+            children: BTreeMap::new(),
             is_dir: true,
             size: 0,
         })
     }
 
-    fn new_file(size: i32) -> Box<Self> {
+    fn new_file(size: u64) -> Box<Self> {
         Box::new(Self {
-            children: HashMap::new(),
+            children: BTreeMap::new(),
             is_dir: false,
             size,
         })
     }
+
+    fn total_size(&self) -> BoxResult<u64> {
+        if self.is_dir {
+            self.children
+                .values()
+                .try_fold(0, |acc, i| add_size(acc, i.total_size()?))
+        } else {
+            Ok(self.size)
+        }
+    }
+
+    fn write_tree(
+        &self,
+        name: &str,
+        depth: usize,
+        dir_sizes: bool,
+        out: &mut String,
+    ) -> BoxResult<()> {
+        write!(out, "{:indent$}- {} ", "", name, indent = depth * 2)?;
+        if !self.is_dir {
+            writeln!(out, "(file, size={})", self.size)?;
+            return Ok(());
+        }
+        if dir_sizes {
+            writeln!(out, "(dir, size={})", self.total_size()?)?;
+        } else {
+            writeln!(out, "(dir)")?;
+        }
+        for (k, v) in &self.children {
+            v.write_tree(k, depth + 1, dir_sizes, out)?;
+        }
+        Ok(())
+    }
+
+    fn write_json(&self, name: &str, out: &mut String) -> BoxResult<()> {
+        write!(out, "{{\"name\":")?;
+        write_json_str(name, out)?;
+        if !self.is_dir {
+            write!(out, ",\"type\":\"file\",\"size\":{}}}", self.size)?;
+            return Ok(());
+        }
+        write!(
+            out,
+            ",\"type\":\"dir\",\"size\":{},\"children\":[",
+            self.total_size()?
+        )?;
+        for (n, (k, v)) in self.children.iter().enumerate() {
+            if n > 0 {
+                write!(out, ",")?;
+            }
+            v.write_json(k, out)?;
+        }
+        write!(out, "]}}")?;
+        Ok(())
+    }
+
+    /// Appends the stats of this node and every node under it to `stats`, returning the total
+    /// size of this node.
+    fn walk(&self, path: &mut Vec<String>, stats: &mut Vec<Stat>) -> BoxResult<u64> {
+        let size = if self.is_dir {
+            let mut total = 0;
+            for (k, v) in &self.children {
+                path.push(k.clone());
+                total = add_size(total, v.walk(path, stats)?)?;
+                path.pop();
+            }
+            total
+        } else {
+            self.size
+        };
+        stats.push(Stat {
+            path: format_path(path),
+            name: path.last().map_or("/", String::as_str).to_string(),
+            is_dir: self.is_dir,
+            size,
+        });
+        Ok(size)
+    }
 }
 
 impl Term {
     fn new() -> Self {
         Self {
             pwd: Vec::new(),
-            root: Node::new_dir(),
+            fs: Fs::new(),
             running: None,
+            line: 0,
+            warnings: Vec::new(),
         }
     }
 
     fn read_input(&mut self, inp: &str) -> BoxResult<()> {
+        self.line += 1;
+        self.read_line(inp)
+            .map_err(|err| format!("line {}: {}", self.line, err).into())
+    }
+
+    fn read_line(&mut self, inp: &str) -> BoxResult<()> {
         lazy_static! {
             static ref RE: Regex = Regex::new(r"^\$ ").unwrap();
         }
         if RE.is_match(inp) {
-            self.running = None;
+            self.finish();
             self.exec(&inp[2..].split_ascii_whitespace().collect::<Vec<_>>()[..])
+        } else if self.running.is_some() {
+            self.read_output_ls(inp)
         } else {
-            match &self.running {
-                Some(running) => match running.as_str() {
-                    "ls" => self.read_output_ls(inp),
-                    _ => Err("Invalid input".into()),
-                },
-                None => Err("Invalid input".into()),
+            Err("Invalid input".into())
+        }
+    }
+
+    /// Completes any `ls` in progress, removing entries that were not relisted. The directory
+    /// leading to the working directory is kept with a warning.
+    fn finish(&mut self) {
+        let Some(mut listing) = self.running.take() else {
+            return;
+        };
+        if let Some(name) = self.pwd.get(listing.path.len()) {
+            if self.pwd.starts_with(&listing.path) && listing.seen.insert(name.clone()) {
+                self.warnings.push(format!(
+                    "line {}: ls of {} did not list {} containing the working directory",
+                    listing.line,
+                    format_path(&listing.path),
+                    format_path(&self.pwd[..=listing.path.len()])
+                ));
             }
         }
+        if let Some(node) = self.fs.lookup_mut(&listing.path) {
+            node.children.retain(|k, _| listing.seen.contains(k));
+        }
+    }
+
+    fn warn(&mut self, msg: String) {
+        self.warnings.push(format!("line {}: {}", self.line, msg));
     }
 
     fn exec(&mut self, cmd: &[&str]) -> BoxResult<()> {
-        match cmd {
-            &["cd", dir] => self.cd(dir),
-            &["ls"] => {
-                self.running = Some("ls".into());
-                Ok(())
-            }
+        match *cmd {
+            ["cd", dir] => self.cd(dir),
+            ["ls"] => self.ls("."),
+            ["ls", dir] => self.ls(dir),
+            ["rm", path] => self.rm(path),
             _ => Err("Invalid cmd".into()),
         }
     }
 
     fn cd(&mut self, dir: &str) -> BoxResult<()> {
-        match dir {
-            "" => return Err("No cd dir".into()),
-            ".." => {
-                self.pwd
-                    .pop()
-                    .ok_or::<BoxError>("No parent directory from root".into())?;
+        let path = resolve_path(&self.pwd, dir)?;
+        let mut node = &mut self.fs.root;
+        let mut created = None;
+        for (n, i) in path.iter().enumerate() {
+            node = match node.children.entry(i.clone()) {
+                Entry::Occupied(e) => e.into_mut(),
+                Entry::Vacant(e) => {
+                    created.get_or_insert(n);
+                    e.insert(Node::new_dir())
+                }
+            };
+            if !node.is_dir {
+                return Err(format!("Cannot cd into file {}", format_path(&path[..=n])).into());
             }
-            "/" => self.pwd.clear(),
-            d => self.pwd.push(d.into()),
+        }
+        if let Some(n) = created {
+            self.warn(format!(
+                "cd into unlisted directory {}",
+                format_path(&path[..=n])
+            ));
+        }
+        self.pwd = path;
+        Ok(())
+    }
+
+    fn ls(&mut self, dir: &str) -> BoxResult<()> {
+        let path = resolve_path(&self.pwd, dir)?;
+        match self.fs.lookup(&path) {
+            Some(node) if node.is_dir => {}
+            Some(_) => return Err(format!("Cannot ls file {}", format_path(&path)).into()),
+            None => return Err(format!("No such directory {}", format_path(&path)).into()),
+        }
+        self.running = Some(Listing {
+            path,
+            line: self.line,
+            seen: BTreeSet::new(),
+        });
+        Ok(())
+    }
+
+    fn rm(&mut self, target: &str) -> BoxResult<()> {
+        let mut path = resolve_path(&self.pwd, target)?;
+        if path.is_empty() {
+            return Err("Cannot rm root".into());
+        }
+        if self.pwd.starts_with(&path) {
+            return Err(format!("Cannot rm {} containing the working directory", target).into());
+        }
+        let name = path.pop().ok_or("Invariant violated")?;
+        let removed = self
+            .fs
+            .lookup_mut(&path)
+            .and_then(|node| node.children.remove(&name));
+        path.push(name);
+        if removed.is_none() {
+            self.warn(format!("rm of unknown entry {}", format_path(&path)));
         }
         Ok(())
     }
@@ -166,62 +631,61 @@ impl Term {
         } else {
             return Err("Invalid ls output".into());
         };
-        if name == "" {
+        if name.is_empty() || name.contains('/') || name == "." || name == ".." {
             return Err("Invalid ls file name".into());
         }
-        if kind == "dir" {
-            self.mkdir(name)
-        } else {
-            self.touch(name, kind.parse()?)
+        let listing = self.running.as_mut().ok_or("Invariant violated")?;
+        if !listing.seen.insert(name.into()) {
+            return Err(format!("Duplicate entry {} in ls output", name).into());
         }
-    }
-
-    fn mkdir_path(&mut self) -> BoxResult<&mut Box<Node>> {
-        let mut node = &mut self.root;
-        for i in self.pwd.iter() {
-            match node.children.entry(i.into()) {
+        let mut path = listing.path.clone();
+        path.push(name.into());
+        let node = self
+            .fs
+            .lookup_mut(&listing.path)
+            .ok_or("Invariant violated")?;
+        if kind == "dir" {
+            match node.children.entry(name.into()) {
                 Entry::Occupied(e) => {
-                    let v = e.get();
-                    if !v.is_dir {
-                        return Err("Mkdir invalid path".into());
+                    if !e.get().is_dir {
+                        return Err(format!(
+                            "File {} is now listed as a directory",
+                            format_path(&path)
+                        )
+                        .into());
                     }
-                    node = e.into_mut();
                 }
                 Entry::Vacant(e) => {
-                    node = e.insert(Node::new_dir());
+                    e.insert(Node::new_dir());
                 }
             }
-        }
-        Ok(node)
-    }
-
-    fn mkdir(&mut self, name: &str) -> BoxResult<()> {
-        let node = self.mkdir_path()?;
-        match node.children.entry(name.into()) {
-            Entry::Occupied(e) => {
-                if !e.get().is_dir {
-                    return Err("Mkdir on non-dir".into());
+        } else {
+            let size = parse_size(kind)
+                .ok_or_else(|| format!("Invalid size {:?} for file {}", kind, name))?;
+            match node.children.entry(name.into()) {
+                Entry::Occupied(mut e) => {
+                    let v = e.get_mut();
+                    if v.is_dir {
+                        return Err(format!(
+                            "Directory {} is now listed as a file",
+                            format_path(&path)
+                        )
+                        .into());
+                    }
+                    if v.size != size {
+                        let prev = v.size;
+                        v.size = size;
+                        self.warn(format!(
+                            "File {} changed size from {} to {}",
+                            format_path(&path),
+                            prev,
+                            size
+                        ));
+                    }
                 }
-            }
-            Entry::Vacant(e) => {
-                e.insert(Node::new_dir());
-            }
-        }
-        Ok(())
-    }
-
-    fn touch(&mut self, name: &str, size: i32) -> BoxResult<()> {
-        let node = self.mkdir_path()?;
-        match node.children.entry(name.into()) {
-            Entry::Occupied(mut e) => {
-                let v = e.get_mut();
-                if v.is_dir {
-                    return Err("Touch file on dir".into());
+                Entry::Vacant(e) => {
+                    e.insert(Node::new_file(size));
                 }
-                v.size = size;
-            }
-            Entry::Vacant(e) => {
-                e.insert(Node::new_file(size));
             }
         }
         Ok(())
@@ -1,5 +1,7 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
+use std::env;
 use std::fs::File;
+use std::io;
 use std::io::prelude::*;
 use std::io::BufReader;
 
@@ -8,230 +10,493 @@ const PUZZLEINPUT: &str = "input.txt";
 type BoxError = Box<dyn std::error::Error>;
 type BoxResult<T> = Result<T, BoxError>;
 
+const USAGE: &str = "Usage: day08 [-i <input>] [-a <height alphabet> | -a base36] [-g <no tree char>] [tree <x> <y> | best | visible-map <file.ppm> | score-map <file.pgm>] | day08 gen <w> <h> [seed]";
+
+const DIGITS: &str = "0123456789";
+const BASE36: &str = "0123456789abcdefghijklmnopqrstuvwxyz";
+
 fn main() -> BoxResult<()> {
-    let file = File::open(PUZZLEINPUT)?;
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    let mut args = args.iter().map(String::as_str).collect::<Vec<_>>();
+    if let ["gen", ref rest @ ..] = args[..] {
+        return match *rest {
+            [w, h] => gen_forest(w.parse()?, h.parse()?, 1),
+            [w, h, seed] => gen_forest(w.parse()?, h.parse()?, seed.parse()?),
+            _ => Err(USAGE.into()),
+        };
+    }
+    let mut path = PUZZLEINPUT;
+    let mut alphabet = DIGITS;
+    let mut gap = '.';
+    while let [flag, v, ..] = args[..] {
+        match flag {
+            "-i" => path = v,
+            "-a" if v == "base36" => alphabet = BASE36,
+            "-a" => alphabet = v,
+            "-g" => gap = v.parse()?,
+            _ => break,
+        }
+        args.drain(..2);
+    }
+    let decoder = Decoder::new(alphabet, gap)?;
+
+    let file = File::open(path)?;
     let reader = BufReader::new(file);
 
     let mut cells = Vec::new();
+    let mut row_lens = Vec::new();
     for line in reader.lines() {
-        let line = line?;
-        let mut row = Vec::with_capacity(line.len());
-        for &b in line.as_bytes() {
-            if b < b'0' || b > b'9' {
-                return Err("Invalid grid cell".into());
-            }
-            row.push((b - b'0') as i32);
+        let start = cells.len();
+        decoder.decode_line(&line?, &mut cells)?;
+        row_lens.push(cells.len() - start);
+    }
+    let w = row_lens.iter().copied().max().unwrap_or(0);
+    let h = row_lens.len();
+    if row_lens.iter().any(|&n| n != w) {
+        // missing cells at the end of shorter rows have no tree
+        let mut padded = Vec::with_capacity(w * h);
+        let mut start = 0;
+        for n in row_lens {
+            padded.extend_from_slice(&cells[start..start + n]);
+            padded.resize(padded.len() + w - n, NO_TREE);
+            start += n;
         }
-        cells.push(row);
+        cells = padded;
     }
 
-    let mut grid = Grid::new(cells)?;
+    let mut grid = Grid::new(w, h, cells)?;
     grid.compute_visible_set();
 
-    println!(
-        "Part 1: {}\nPart 2: {}",
-        grid.visible.len(),
-        grid.max_power()
-    );
+    match args[..] {
+        [] => println!(
+            "Part 1: {}\nPart 2: {}",
+            grid.count_visible(),
+            grid.max_power()
+        ),
+        ["tree", x, y] => {
+            let (x, y) = (x.parse()?, y.parse()?);
+            let tree = grid.tree(x, y).ok_or("Tree out of bounds")?;
+            if tree.height == NO_TREE {
+                println!("No tree at ({}, {})", x, y);
+                return Ok(());
+            }
+            let edges = DIRS
+                .iter()
+                .filter(|&&(dir, _)| tree.visible & dir != 0)
+                .map(|&(_, name)| name)
+                .collect::<Vec<_>>();
+            println!(
+                "Tree ({}, {}): height {}, visible from {}",
+                x,
+                y,
+                tree.height,
+                if edges.is_empty() {
+                    "no edge".to_string()
+                } else {
+                    edges.join(", ")
+                }
+            );
+            println!(
+                "Viewing distances: {}",
+                DIRS.iter()
+                    .zip(tree.distances)
+                    .map(|(&(_, name), d)| format!("{} {}", name, d))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+            println!("Scenic score: {}", tree.power);
+        }
+        ["best"] => {
+            println!("Best scenic score: {}", grid.max_power());
+            for (x, y) in grid.best_trees() {
+                println!("({}, {})", x, y);
+            }
+        }
+        ["visible-map", out] => {
+            grid.write_visible_map(&mut io::BufWriter::new(File::create(out)?))?
+        }
+        ["score-map", out] => grid.write_power_map(&mut io::BufWriter::new(File::create(out)?))?,
+        _ => return Err(USAGE.into()),
+    }
+
+    Ok(())
+}
 
+/// Writes a random forest of digit heights to stdout.
+fn gen_forest(w: usize, h: usize, seed: u64) -> BoxResult<()> {
+    let mut out = io::BufWriter::new(io::stdout().lock());
+    let mut state = seed.max(1);
+    let mut line = vec![b'0'; w + 1];
+    line[w] = b'\n';
+    for _ in 0..h {
+        for i in line.iter_mut().take(w) {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            *i = b'0' + (state % 10) as u8;
+        }
+        out.write_all(&line)?;
+    }
     Ok(())
 }
 
+/// Height of a cell without a tree, which ends viewing distances but lets trees behind it be
+/// seen from the edge of the forest
+const NO_TREE: u8 = u8::MAX;
+
+/// Maps characters to tree heights by their position in an alphabet.
+struct Decoder {
+    ascii: [Option<u8>; 128],
+    other: HashMap<char, u8>,
+}
+
+impl Decoder {
+    fn new(alphabet: &str, gap: char) -> BoxResult<Self> {
+        let mut res = Self {
+            ascii: [None; 128],
+            other: HashMap::new(),
+        };
+        let mut len = 0;
+        for (i, c) in alphabet.chars().enumerate() {
+            if i >= NO_TREE as usize || !res.insert(c, i as u8) {
+                return Err("Invalid height alphabet".into());
+            }
+            len += 1;
+        }
+        if len == 0 {
+            return Err("Invalid height alphabet".into());
+        }
+        if !res.insert(gap, NO_TREE) {
+            return Err("No tree character is in the height alphabet".into());
+        }
+        Ok(res)
+    }
+
+    /// Adds a character to the lookup tables, returning false if it is already there.
+    fn insert(&mut self, c: char, height: u8) -> bool {
+        match self.ascii.get_mut(c as usize) {
+            Some(slot) => slot.replace(height).is_none(),
+            None => self.other.insert(c, height).is_none(),
+        }
+    }
+
+    fn decode_line(&self, line: &str, cells: &mut Vec<u8>) -> BoxResult<()> {
+        if line.is_ascii() {
+            for b in line.bytes() {
+                cells.push(
+                    self.ascii[b as usize]
+                        .ok_or_else(|| format!("Invalid grid cell {:?}", b as char))?,
+                );
+            }
+        } else {
+            for c in line.chars() {
+                cells.push(self.decode(c)?);
+            }
+        }
+        Ok(())
+    }
+
+    fn decode(&self, c: char) -> BoxResult<u8> {
+        match self.ascii.get(c as usize) {
+            Some(&height) => height,
+            None => self.other.get(&c).copied(),
+        }
+        .ok_or_else(|| format!("Invalid grid cell {:?}", c).into())
+    }
+}
+
+const DIR_TOP: u8 = 1;
+const DIR_RIGHT: u8 = 2;
+const DIR_BOTTOM: u8 = 4;
+const DIR_LEFT: u8 = 8;
+const DIRS: [(u8, &str); 4] = [
+    (DIR_TOP, "top"),
+    (DIR_RIGHT, "right"),
+    (DIR_BOTTOM, "bottom"),
+    (DIR_LEFT, "left"),
+];
+
+struct Tree {
+    height: u8,
+    visible: u8,
+    /// Viewing distances in `DIRS` order
+    distances: [usize; 4],
+    power: u64,
+}
+
+/// A forest stored as flat row-major arrays.
 struct Grid {
     w: usize,
     h: usize,
-    grid: Vec<Vec<i32>>,
-    visible: HashSet<Tuple2>,
-    power: HashMap<Tuple2, Tuple5>,
+    grid: Vec<u8>,
+    /// Bitset of the `DIR_*` edges from which each tree is visible
+    visible: Vec<u8>,
+    power: Vec<u64>,
 }
 
 impl Grid {
-    fn new(grid: Vec<Vec<i32>>) -> BoxResult<Self> {
-        let h = grid.len();
-        if h == 0 {
+    fn new(w: usize, h: usize, grid: Vec<u8>) -> BoxResult<Self> {
+        if w == 0 || h == 0 {
             return Err("Empty grid".into());
         }
-        let w = grid[0].len();
-        for i in &grid {
-            if i.len() != w {
-                return Err("Not rectangular grid".into());
-            }
+        if grid.len() != w * h {
+            return Err("Not rectangular grid".into());
         }
         Ok(Self {
             w,
             h,
             grid,
-            visible: HashSet::new(),
-            power: HashMap::new(),
+            visible: Vec::new(),
+            power: Vec::new(),
         })
     }
 
-    fn max_power(&self) -> i32 {
-        self.power.values().fold(0, |acc, i| {
-            let k = i.get_power();
-            if k > acc {
-                k
-            } else {
-                acc
-            }
-        })
+    fn count_visible(&self) -> usize {
+        self.visible.iter().filter(|&&i| i != 0).count()
     }
 
-    fn compute_visible_set(&mut self) {
-        for y in 0..self.h {
-            let mut tallest = -1;
-            for x in 0..self.w {
-                let pos = Tuple2 {
-                    x: x as i32,
-                    y: y as i32,
-                };
-                let k = self.grid[y][x];
-
-                if k > tallest {
-                    tallest = k;
-                    self.visible.insert(pos);
-                }
-
-                self.power.insert(
-                    pos,
-                    Tuple5 {
-                        h: k,
-                        t: 0,
-                        r: 0,
-                        b: 0,
-                        l: 0,
-                    },
-                );
+    fn max_power(&self) -> u64 {
+        self.power.iter().copied().max().unwrap_or(0)
+    }
 
-                if x == 0 {
-                    continue;
+    fn tree(&self, x: usize, y: usize) -> Option<Tree> {
+        if x >= self.w || y >= self.h {
+            return None;
+        }
+        let idx = y * self.w + x;
+        let height = self.grid[idx];
+        let distances = DIRS.map(|(dir, _)| {
+            let (dx, dy): (isize, isize) = match dir {
+                DIR_TOP => (0, -1),
+                DIR_RIGHT => (1, 0),
+                DIR_BOTTOM => (0, 1),
+                _ => (-1, 0),
+            };
+            let (mut cx, mut cy) = (x, y);
+            let mut distance = 0;
+            while let (Some(nx), Some(ny)) = (cx.checked_add_signed(dx), cy.checked_add_signed(dy))
+            {
+                if nx >= self.w || ny >= self.h || self.grid[ny * self.w + nx] == NO_TREE {
+                    break;
                 }
-
-                let mut visible = 1;
-                let mut prev = pos.delta(-1, 0);
-                let mut prev_power = self.power.get(&prev).unwrap();
-                while k > prev_power.h && prev_power.l > 0 {
-                    visible += prev_power.l;
-                    prev = prev.delta(-prev_power.l, 0);
-                    prev_power = self.power.get(&prev).unwrap();
+                distance += 1;
+                if self.grid[ny * self.w + nx] >= height {
+                    break;
                 }
-                self.power.get_mut(&pos).unwrap().l = visible;
+                (cx, cy) = (nx, ny);
             }
+            distance
+        });
+        Some(Tree {
+            height,
+            visible: self.visible[idx],
+            distances,
+            power: self.power[idx],
+        })
+    }
 
-            tallest = -1;
-            for x in (0..self.w).rev() {
-                let pos = Tuple2 {
-                    x: x as i32,
-                    y: y as i32,
-                };
-                let k = self.grid[y][x];
-
-                if k > tallest {
-                    tallest = k;
-                    self.visible.insert(pos);
-                }
-
-                if x == self.w - 1 {
-                    continue;
-                }
+    /// Returns the positions of every tree with the maximum scenic score in row-major order.
+    fn best_trees(&self) -> Vec<(usize, usize)> {
+        let max = self.max_power();
+        self.power
+            .iter()
+            .enumerate()
+            .filter(|&(_, &p)| p == max)
+            .map(|(idx, _)| (idx % self.w, idx / self.w))
+            .collect()
+    }
 
-                let mut visible = 1;
-                let mut prev = pos.delta(1, 0);
-                let mut prev_power = self.power.get(&prev).unwrap();
-                while k > prev_power.h && prev_power.r > 0 {
-                    visible += prev_power.r;
-                    prev = prev.delta(prev_power.r, 0);
-                    prev_power = self.power.get(&prev).unwrap();
-                }
-                self.power.get_mut(&pos).unwrap().r = visible;
+    /// Writes a plain PPM image where visible trees are red when visible from a horizontal edge
+    /// and blue when visible from a vertical edge, green shows the height of each tree, and
+    /// cells without a tree are black.
+    fn write_visible_map(&self, out: &mut impl Write) -> BoxResult<()> {
+        let max_height = self
+            .grid
+            .iter()
+            .filter(|&&i| i != NO_TREE)
+            .max()
+            .map_or(1, |&i| i.max(1) as u64);
+        let mut img = PlainNetpbm::new(out, "P3", self.w, self.h, 255)?;
+        for (&v, &height) in self.visible.iter().zip(&self.grid) {
+            if height == NO_TREE {
+                img.write(0)?;
+                img.write(0)?;
+                img.write(0)?;
+                continue;
             }
+            let horizontal = v & (DIR_LEFT | DIR_RIGHT) != 0;
+            let vertical = v & (DIR_TOP | DIR_BOTTOM) != 0;
+            img.write(if horizontal { 255 } else { 0 })?;
+            img.write(height as u64 * 255 / max_height)?;
+            img.write(if vertical { 255 } else { 0 })?;
         }
+        img.finish()
+    }
 
-        for x in 0..self.w {
-            let mut tallest = -1;
-            for y in 0..self.h {
-                let pos = Tuple2 {
-                    x: x as i32,
-                    y: y as i32,
-                };
-                let k = self.grid[y][x];
-
-                if k > tallest {
-                    tallest = k;
-                    self.visible.insert(pos);
-                }
+    /// Writes a plain PGM image of scenic scores, scaled logarithmically so that lower scores
+    /// remain distinguishable.
+    fn write_power_map(&self, out: &mut impl Write) -> BoxResult<()> {
+        let max = (self.max_power() as f64).ln_1p();
+        let mut img = PlainNetpbm::new(out, "P2", self.w, self.h, 255)?;
+        for &p in &self.power {
+            img.write(if max > 0.0 {
+                ((p as f64).ln_1p() / max * 255.0).round() as u64
+            } else {
+                0
+            })?;
+        }
+        img.finish()
+    }
 
-                if y == 0 {
-                    continue;
-                }
+    fn compute_visible_set(&mut self) {
+        self.visible = vec![0; self.w * self.h];
+        self.power = vec![1; self.w * self.h];
+        for dir in [DIR_TOP, DIR_RIGHT, DIR_BOTTOM, DIR_LEFT] {
+            self.scan(dir);
+        }
+    }
 
-                let mut visible = 1;
-                let mut prev = pos.delta(0, -1);
-                let mut prev_power = self.power.get(&prev).unwrap();
-                while k > prev_power.h && prev_power.t > 0 {
-                    visible += prev_power.t;
-                    prev = prev.delta(0, -prev_power.t);
-                    prev_power = self.power.get(&prev).unwrap();
+    /// Scans every line of trees inward from the edge `dir`, marking the trees visible from
+    /// that edge and multiplying each tree's viewing distance toward that edge into its scenic
+    /// score.
+    fn scan(&mut self, dir: u8) {
+        let (w, h) = (self.w, self.h);
+        let horizontal = dir == DIR_LEFT || dir == DIR_RIGHT;
+        let (lanes, len) = if horizontal { (h, w) } else { (w, h) };
+        let index = |lane: usize, k: usize| match dir {
+            DIR_TOP => k * w + lane,
+            DIR_RIGHT => lane * w + w - 1 - k,
+            DIR_BOTTOM => (h - 1 - k) * w + lane,
+            _ => lane * w + k,
+        };
+        // positions and heights of the trees in each line that may still block later trees, in
+        // strictly decreasing height order, above an entry holding the position of the first
+        // tree after the edge or the most recent cell without a tree
+        let mut stacks = vec![vec![(0, NO_TREE)]; lanes];
+        // height of the tallest tree between the edge and the current one in each line
+        let mut tallest = vec![None; lanes];
+        // visit trees in memory order, so rows are scanned one at a time while columns are
+        // scanned together
+        if horizontal {
+            for (lane, (stack, tallest)) in stacks.iter_mut().zip(&mut tallest).enumerate() {
+                for k in 0..len {
+                    self.scan_tree(stack, tallest, k, index(lane, k), dir);
                 }
-                self.power.get_mut(&pos).unwrap().t = visible;
             }
-
-            tallest = -1;
-            for y in (0..self.h).rev() {
-                let pos = Tuple2 {
-                    x: x as i32,
-                    y: y as i32,
-                };
-                let k = self.grid[y][x];
-
-                if k > tallest {
-                    tallest = k;
-                    self.visible.insert(pos);
-                }
-
-                if y == self.h - 1 {
-                    continue;
+        } else {
+            for k in 0..len {
+                for (lane, (stack, tallest)) in stacks.iter_mut().zip(&mut tallest).enumerate() {
+                    self.scan_tree(stack, tallest, k, index(lane, k), dir);
                 }
-
-                let mut visible = 1;
-                let mut prev = pos.delta(0, 1);
-                let mut prev_power = self.power.get(&prev).unwrap();
-                while k > prev_power.h && prev_power.b > 0 {
-                    visible += prev_power.b;
-                    prev = prev.delta(0, prev_power.b);
-                    prev_power = self.power.get(&prev).unwrap();
-                }
-                self.power.get_mut(&pos).unwrap().b = visible;
             }
         }
     }
+
+    fn scan_tree(
+        &mut self,
+        stack: &mut Vec<(usize, u8)>,
+        tallest: &mut Option<u8>,
+        k: usize,
+        idx: usize,
+        dir: u8,
+    ) {
+        let v = self.grid[idx];
+        if v == NO_TREE {
+            stack.clear();
+            stack.push((k + 1, v));
+            self.power[idx] = 0;
+            return;
+        }
+        if tallest.is_none_or(|t| t < v) {
+            self.visible[idx] |= dir;
+            *tallest = Some(v);
+        }
+        // the bottom entry is never popped, since it is higher than every tree
+        while matches!(stack.last(), Some(&(_, prev)) if prev < v) {
+            stack.pop();
+        }
+        let Some(&(pos, prev)) = stack.last() else {
+            return;
+        };
+        self.power[idx] *= (k - pos) as u64;
+        if prev == v {
+            stack.pop();
+        }
+        stack.push((k, v));
+    }
 }
 
-struct Tuple5 {
-    h: i32,
-    t: i32,
-    r: i32,
-    b: i32,
-    l: i32,
+/// Writes the samples of a plain (ASCII) netpbm image, keeping lines within 70 characters.
+struct PlainNetpbm<'a, W: Write> {
+    out: &'a mut W,
+    line_len: usize,
 }
 
-impl Tuple5 {
-    fn get_power(&self) -> i32 {
-        self.t * self.r * self.b * self.l
+impl<'a, W: Write> PlainNetpbm<'a, W> {
+    fn new(out: &'a mut W, magic: &str, w: usize, h: usize, maxval: u64) -> BoxResult<Self> {
+        writeln!(out, "{}\n{} {}\n{}", magic, w, h, maxval)?;
+        Ok(Self { out, line_len: 0 })
     }
-}
 
-#[derive(Clone, Copy, Eq, Hash, PartialEq)]
-struct Tuple2 {
-    y: i32,
-    x: i32,
+    fn write(&mut self, v: u64) -> BoxResult<()> {
+        let s = v.to_string();
+        if self.line_len > 0 && self.line_len + 1 + s.len() > 70 {
+            writeln!(self.out)?;
+            self.line_len = 0;
+        }
+        if self.line_len > 0 {
+            write!(self.out, " ")?;
+            self.line_len += 1;
+        }
+        write!(self.out, "{}", s)?;
+        self.line_len += s.len();
+        Ok(())
+    }
+
+    fn finish(self) -> BoxResult<()> {
+        writeln!(self.out)?;
+        self.out.flush()?;
+        Ok(())
+    }
 }
 
-impl Tuple2 {
-    fn delta(&self, x: i32, y: i32) -> Self {
-        Self {
-            x: self.x + x,
-            y: self.y + y,
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_grid(rows: &[&str]) -> Grid {
+        let decoder = Decoder::new(DIGITS, '.').unwrap();
+        let mut cells = Vec::new();
+        for row in rows {
+            decoder.decode_line(row, &mut cells).unwrap();
         }
+        let mut grid = Grid::new(rows[0].len(), rows.len(), cells).unwrap();
+        grid.compute_visible_set();
+        grid
+    }
+
+    #[test]
+    fn puzzle_example() {
+        let grid = parse_grid(&["30373", "25512", "65332", "33549", "35390"]);
+        assert_eq!(grid.count_visible(), 21);
+        assert_eq!(grid.max_power(), 8);
+    }
+
+    #[test]
+    fn hole_does_not_expose_trees_behind_it() {
+        let grid = parse_grid(&["99999", "99999", "99.99", "99199", "99999", "99999"]);
+        let tree = grid.tree(2, 3).unwrap();
+        assert_eq!(tree.visible, 0);
+        assert_eq!(tree.distances, [0, 1, 1, 1]);
+        // only the edge trees, since the trees around the hole are walled in by the 9s beyond it
+        assert_eq!(grid.count_visible(), 18);
+    }
+
+    #[test]
+    fn tree_seen_past_hole() {
+        let grid = parse_grid(&["12.34"]);
+        let tree = grid.tree(3, 0).unwrap();
+        assert_eq!(tree.visible, DIR_TOP | DIR_BOTTOM | DIR_LEFT);
+        assert_eq!(tree.distances, [0, 1, 0, 0]);
     }
 }
@@ -1,41 +1,185 @@
-use std::collections::HashSet;
+use std::env;
+use std::fmt;
 use std::fs::File;
 use std::io::prelude::*;
-use std::io::BufReader;
+use std::io::{self, BufReader, BufWriter};
+
+mod render;
+mod visits;
+
+use visits::Visits;
 
 const PUZZLEINPUT: &str = "input.txt";
+const PUZZLE_KNOTS: [usize; 2] = [2, 10];
+const USAGE: &str = "Usage: day09 [-i <input>] [-e bulk | steps | verify] [-r steps | moves | map] [-o <output>] [<knots>...]";
 
 type BoxError = Box<dyn std::error::Error>;
 type BoxResult<T> = Result<T, BoxError>;
 
+/// How often to draw the rope while it moves
+#[derive(Clone, Copy, PartialEq)]
+enum Render {
+    Steps,
+    Moves,
+    /// Only draw the cells visited by the tail at the end
+    Map,
+}
+
+/// How to apply motions that are not drawn step by step
+#[derive(Clone, Copy, PartialEq)]
+enum Engine {
+    /// Move the whole rope at once when its shape stops changing
+    Bulk,
+    Steps,
+    /// Use the bulk engine and check its visit sets against the step engine
+    Verify,
+}
+
 fn main() -> BoxResult<()> {
-    let file = File::open(PUZZLEINPUT)?;
-    let reader = BufReader::new(file);
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    let mut args = args.iter().map(String::as_str).collect::<Vec<_>>();
+    let mut path = PUZZLEINPUT;
+    let mut render = None;
+    let mut output = None;
+    let mut engine = Engine::Bulk;
+    while let [flag, v, ..] = args[..] {
+        match flag {
+            "-i" => path = v,
+            "-r" => {
+                render = Some(match v {
+                    "steps" => Render::Steps,
+                    "moves" => Render::Moves,
+                    "map" => Render::Map,
+                    _ => return Err(USAGE.into()),
+                })
+            }
+            "-e" => {
+                engine = match v {
+                    "bulk" => Engine::Bulk,
+                    "steps" => Engine::Steps,
+                    "verify" => Engine::Verify,
+                    _ => return Err(USAGE.into()),
+                }
+            }
+            "-o" => output = Some(v),
+            _ => break,
+        }
+        args.drain(..2);
+    }
+    let knots = args
+        .iter()
+        .map(|i| match i.parse() {
+            Ok(n) if n > 0 => Ok(n),
+            _ => Err(USAGE),
+        })
+        .collect::<Result<Vec<usize>, _>>()?;
+    let custom = !knots.is_empty();
+    let knots = if custom { knots } else { PUZZLE_KNOTS.to_vec() };
 
-    let mut rope1 = Rope::new(1);
-    let mut rope2 = Rope::new(9);
+    let file = File::open(path)?;
+    let reader = BufReader::new(file);
 
+    let mut motions = Vec::new();
     for line in reader.lines() {
-        let line = line?;
-        let (dir, countstr) = line
-            .split_once(' ')
-            .ok_or::<BoxError>("Invalid line".into())?;
-        let count = countstr.parse::<i32>()?;
-        for _ in 0..count {
-            rope1.apply_dir(&dir)?;
-            rope2.apply_dir(&dir)?;
+        motions.push(Motion::parse(&line?)?);
+    }
+    let bounds = Bounds::of_motions(&motions)?;
+
+    let mut out: Box<dyn Write> = match output {
+        Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+        None => Box::new(BufWriter::new(io::stdout().lock())),
+    };
+
+    let mut ropes = Vec::new();
+    for n in knots {
+        let mut rope = Rope::new(n, custom);
+        match render {
+            Some(Render::Map) | None => {
+                for m in &motions {
+                    if engine == Engine::Steps {
+                        rope.step_all(m);
+                    } else {
+                        rope.advance(m);
+                    }
+                }
+            }
+            Some(render) => {
+                writeln!(out, "== Initial State ==\n")?;
+                render::write_rope(&rope, &bounds, &mut out)?;
+                for m in &motions {
+                    writeln!(out, "== {} ==\n", m)?;
+                    if render == Render::Steps {
+                        for _ in 0..m.count {
+                            rope.step(&m.dir);
+                            render::write_rope(&rope, &bounds, &mut out)?;
+                        }
+                    } else {
+                        if engine == Engine::Steps {
+                            rope.step_all(m);
+                        } else {
+                            rope.advance(m);
+                        }
+                        render::write_rope(&rope, &bounds, &mut out)?;
+                    }
+                }
+            }
+        }
+        if engine == Engine::Verify {
+            let mut check = Rope::new(n, custom);
+            for m in &motions {
+                check.step_all(m);
+            }
+            for (i, (a, b)) in rope.history.iter().zip(&check.history).enumerate() {
+                let (count, cells) = (a.len(), a.cells());
+                if cells != b.cells() || count != cells.len() as u64 {
+                    return Err(format!(
+                        "Engines disagree on knot {} of a rope of {} knots: {} and {} cells",
+                        knot_label(i + rope.tracked),
+                        n,
+                        count,
+                        b.len()
+                    )
+                    .into());
+                }
+            }
+        }
+        if render.is_some() {
+            writeln!(out, "== Visited by the tail of {} knots ==\n", n)?;
+            render::write_visits(rope.tail_history(), &bounds, &mut out)?;
         }
+        ropes.push(rope);
     }
+    out.flush()?;
+    drop(out);
 
-    println!(
-        "Part 1: {}\nPart 2: {}",
-        rope1.history.len(),
-        rope2.history.len()
-    );
+    if !custom {
+        println!(
+            "Part 1: {}\nPart 2: {}",
+            ropes[0].tail_history().len(),
+            ropes[1].tail_history().len()
+        );
+        return Ok(());
+    }
+
+    for rope in &ropes {
+        println!("Rope of {} knots", rope.knots.len());
+        for (i, history) in rope.history.iter().enumerate() {
+            println!("Knot {}: {}", knot_label(i + rope.tracked), history.len());
+        }
+    }
+    println!("Bounds: {}", bounds);
 
     Ok(())
 }
 
+fn knot_label(i: usize) -> String {
+    if i == 0 {
+        "H".to_string()
+    } else {
+        i.to_string()
+    }
+}
+
 #[derive(Clone, Copy, Eq, Hash, PartialEq)]
 struct Tuple2 {
     x: i32,
@@ -43,15 +187,25 @@ struct Tuple2 {
 }
 
 impl Tuple2 {
-    fn apply_dir(&mut self, dir: &str) -> BoxResult<()> {
-        match dir {
-            "U" => self.y -= 1,
-            "R" => self.x += 1,
-            "D" => self.y += 1,
-            "L" => self.x -= 1,
-            _ => return Err("Invalid direction".into()),
+    /// Parses a direction made of at most one vertical and one horizontal step, e.g. `U` or
+    /// `DL`.
+    fn parse_dir(dir: &str) -> BoxResult<Self> {
+        let mut res = Self { x: 0, y: 0 };
+        for c in dir.chars() {
+            match c {
+                'U' | 'D' if res.y != 0 => return Err("Invalid direction".into()),
+                'R' | 'L' if res.x != 0 => return Err("Invalid direction".into()),
+                'U' => res.y = -1,
+                'R' => res.x = 1,
+                'D' => res.y = 1,
+                'L' => res.x = -1,
+                _ => return Err("Invalid direction".into()),
+            }
         }
-        Ok(())
+        if res.max_mag() == 0 {
+            return Err("Invalid direction".into());
+        }
+        Ok(res)
     }
 
     fn delta(&mut self, p: &Self) {
@@ -88,37 +242,264 @@ fn unit_dir(a: i32) -> i32 {
     }
 }
 
+/// A line of the input moving the head `count` steps in `dir`.
+struct Motion {
+    dir: Tuple2,
+    count: i32,
+}
+
+impl fmt::Display for Motion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.dir.y {
+            -1 => write!(f, "U")?,
+            1 => write!(f, "D")?,
+            _ => {}
+        }
+        match self.dir.x {
+            -1 => write!(f, "L")?,
+            1 => write!(f, "R")?,
+            _ => {}
+        }
+        write!(f, " {}", self.count)
+    }
+}
+
+impl Motion {
+    fn parse(line: &str) -> BoxResult<Self> {
+        let (dir, countstr) = line.split_once(' ').ok_or("Invalid line")?;
+        let count = countstr.parse()?;
+        if count < 0 {
+            return Err("Invalid step count".into());
+        }
+        Ok(Self {
+            dir: Tuple2::parse_dir(dir)?,
+            count,
+        })
+    }
+}
+
+/// The smallest rectangle containing a set of positions.
+struct Bounds {
+    min: Tuple2,
+    max: Tuple2,
+}
+
+impl Bounds {
+    fn new(p: Tuple2) -> Self {
+        Self { min: p, max: p }
+    }
+
+    /// Returns the bounds of every position of the head, which contain every position of the
+    /// knots following it.
+    fn of_motions(motions: &[Motion]) -> BoxResult<Self> {
+        let mut pos = Tuple2 { x: 0, y: 0 };
+        let mut res = Self::new(pos);
+        for m in motions {
+            pos.x = pos
+                .x
+                .checked_add(m.dir.x * m.count)
+                .ok_or("Motion out of range")?;
+            pos.y = pos
+                .y
+                .checked_add(m.dir.y * m.count)
+                .ok_or("Motion out of range")?;
+            res.extend(&pos);
+        }
+        if res.max.x.checked_sub(res.min.x).is_none() || res.max.y.checked_sub(res.min.y).is_none()
+        {
+            return Err("Motion out of range".into());
+        }
+        Ok(res)
+    }
+
+    fn extend(&mut self, p: &Tuple2) {
+        self.min.x = self.min.x.min(p.x);
+        self.min.y = self.min.y.min(p.y);
+        self.max.x = self.max.x.max(p.x);
+        self.max.y = self.max.y.max(p.y);
+    }
+
+    fn width(&self) -> usize {
+        (self.max.x - self.min.x) as usize + 1
+    }
+
+    fn height(&self) -> usize {
+        (self.max.y - self.min.y) as usize + 1
+    }
+}
+
+impl fmt::Display for Bounds {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "({}, {}) to ({}, {}), {}x{}",
+            self.min.x,
+            self.min.y,
+            self.max.x,
+            self.max.y,
+            self.width(),
+            self.height()
+        )
+    }
+}
+
 struct Rope {
-    h: Tuple2,
-    t: Vec<Tuple2>,
-    history: HashSet<Tuple2>,
+    /// Positions of the knots, starting with the head
+    knots: Vec<Tuple2>,
+    /// Index of the first knot whose visits are recorded
+    tracked: usize,
+    /// Positions visited by each recorded knot
+    history: Vec<Visits>,
 }
 
 impl Rope {
-    fn new(size: usize) -> Self {
-        let mut history = HashSet::new();
-        history.insert(Tuple2 { x: 0, y: 0 });
+    /// Creates a rope recording the visits of every knot, or only those of the tail.
+    fn new(size: usize, track_all: bool) -> Self {
+        let start = Tuple2 { x: 0, y: 0 };
+        let tracked = if track_all { 0 } else { size - 1 };
         Self {
-            h: Tuple2 { x: 0, y: 0 },
-            t: vec![Tuple2 { x: 0, y: 0 }; size],
-            history,
-        }
-    }
-
-    fn apply_dir(&mut self, dir: &str) -> BoxResult<()> {
-        self.h.apply_dir(dir)?;
-        let mut next = &self.h;
-        let last = self.t.len() - 1;
-        for i in 0..self.t.len() {
-            let k = self.t[i].dist(&next);
-            if k.max_mag() > 1 {
-                self.t[i].delta(&k.dir());
-                if i == last {
-                    self.history.insert(self.t[i]);
+            knots: vec![start; size],
+            tracked,
+            history: (tracked..size)
+                .map(|_| {
+                    let mut visits = Visits::new();
+                    visits.insert(start);
+                    visits
+                })
+                .collect(),
+        }
+    }
+
+    fn record(&mut self, i: usize) {
+        if i >= self.tracked {
+            self.history[i - self.tracked].insert(self.knots[i]);
+        }
+    }
+
+    fn tail_history(&self) -> &Visits {
+        &self.history[self.history.len() - 1]
+    }
+
+    /// Moves the head one step and returns whether every knot moved the same way, leaving
+    /// the shape of the rope unchanged.
+    fn step(&mut self, dir: &Tuple2) -> bool {
+        self.knots[0].delta(dir);
+        self.record(0);
+        let mut rigid = true;
+        for i in 1..self.knots.len() {
+            let k = self.knots[i].dist(&self.knots[i - 1]);
+            if k.max_mag() <= 1 {
+                // the remaining knots stay put when this one does
+                return false;
+            }
+            let d = k.dir();
+            rigid &= d == *dir;
+            self.knots[i].delta(&d);
+            self.record(i);
+        }
+        rigid
+    }
+
+    /// Moves the head `count` steps one step at a time.
+    fn step_all(&mut self, m: &Motion) {
+        for _ in 0..m.count {
+            self.step(&m.dir);
+        }
+    }
+
+    /// Moves the head `count` steps. Once a step leaves the shape of the rope unchanged, every
+    /// following step of the motion does too, so the rest of the motion is applied to all knots
+    /// at once.
+    fn advance(&mut self, m: &Motion) {
+        let mut left = m.count;
+        while left > 0 {
+            left -= 1;
+            if self.step(&m.dir) {
+                break;
+            }
+        }
+        if left == 0 {
+            return;
+        }
+        for (i, p) in self.knots.iter_mut().enumerate() {
+            let start = *p;
+            if i >= self.tracked {
+                let first = Tuple2 {
+                    x: start.x + m.dir.x,
+                    y: start.y + m.dir.y,
+                };
+                self.history[i - self.tracked].insert_run(first, &m.dir, left - 1);
+            }
+            p.x += m.dir.x * left;
+            p.y += m.dir.y * left;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DIRS: [&str; 8] = ["U", "R", "D", "L", "UR", "DR", "DL", "UL"];
+
+    fn parse_motions(lines: &[&str]) -> Vec<Motion> {
+        lines.iter().map(|i| Motion::parse(i).unwrap()).collect()
+    }
+
+    /// Generates motions in every direction with counts from 0 to 40.
+    fn gen_motions(mut seed: u64, n: usize) -> Vec<Motion> {
+        let mut next = move || {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            seed
+        };
+        (0..n)
+            .map(|_| Motion {
+                dir: Tuple2::parse_dir(DIRS[next() as usize % DIRS.len()]).unwrap(),
+                count: (next() % 41) as i32,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn bulk_matches_steps() {
+        for seed in 1..=20 {
+            let motions = gen_motions(seed, 200);
+            for knots in [1, 2, 10] {
+                let mut bulk = Rope::new(knots, true);
+                let mut steps = Rope::new(knots, true);
+                for m in &motions {
+                    bulk.advance(m);
+                    steps.step_all(m);
+                }
+                assert!(bulk.knots == steps.knots, "seed {} knots {}", seed, knots);
+                for (i, (a, b)) in bulk.history.iter().zip(&steps.history).enumerate() {
+                    let cells = b.cells();
+                    assert!(
+                        a.cells() == cells,
+                        "seed {} knots {} knot {}",
+                        seed,
+                        knots,
+                        i
+                    );
+                    assert_eq!(a.len(), cells.len() as u64);
+                    assert_eq!(b.len(), cells.len() as u64);
                 }
             }
-            next = &self.t[i];
         }
-        Ok(())
+    }
+
+    #[test]
+    fn puzzle_examples() {
+        let small = parse_motions(&["R 4", "U 4", "L 3", "D 1", "R 4", "D 1", "L 5", "R 2"]);
+        let large = parse_motions(&["R 5", "U 8", "L 8", "D 3", "R 17", "D 10", "L 25", "U 20"]);
+        for (motions, knots, visited) in [(&small, 2, 13), (&small, 10, 1), (&large, 10, 36)] {
+            let mut rope = Rope::new(knots, false);
+            for m in motions {
+                rope.advance(m);
+            }
+            assert_eq!(rope.tail_history().len(), visited);
+        }
     }
 }
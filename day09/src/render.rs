@@ -0,0 +1,63 @@
+use super::{Bounds, Rope, Tuple2, Visits};
+use std::io::{self, Write};
+
+/// A grid of characters covering `bounds`, drawn in the style of the puzzle text.
+struct Canvas<'a> {
+    bounds: &'a Bounds,
+    cells: Vec<u8>,
+}
+
+impl<'a> Canvas<'a> {
+    fn new(bounds: &'a Bounds) -> Self {
+        let mut res = Self {
+            bounds,
+            cells: vec![b'.'; bounds.width() * bounds.height()],
+        };
+        res.set(&Tuple2 { x: 0, y: 0 }, b's');
+        res
+    }
+
+    fn set(&mut self, p: &Tuple2, c: u8) {
+        let x = (p.x - self.bounds.min.x) as usize;
+        let y = (p.y - self.bounds.min.y) as usize;
+        self.cells[y * self.bounds.width() + x] = c;
+    }
+
+    fn write(&self, out: &mut impl Write) -> io::Result<()> {
+        for row in self.cells.chunks(self.bounds.width()) {
+            out.write_all(row)?;
+            writeln!(out)?;
+        }
+        writeln!(out)
+    }
+}
+
+/// Returns the marker of the knot `i` of a rope with `len` knots.
+fn knot_marker(i: usize, len: usize) -> u8 {
+    match i {
+        0 => b'H',
+        _ if len == 2 => b'T',
+        1..=9 => b'0' + i as u8,
+        _ => b'+',
+    }
+}
+
+/// Writes the knots of a rope, with knots nearer the head drawn over the ones behind them.
+pub fn write_rope(rope: &Rope, bounds: &Bounds, out: &mut impl Write) -> io::Result<()> {
+    let mut canvas = Canvas::new(bounds);
+    for (i, p) in rope.knots.iter().enumerate().rev() {
+        canvas.set(p, knot_marker(i, rope.knots.len()));
+    }
+    canvas.write(out)
+}
+
+/// Writes the positions visited by a knot as `#`.
+pub fn write_visits(history: &Visits, bounds: &Bounds, out: &mut impl Write) -> io::Result<()> {
+    let mut canvas = Canvas::new(bounds);
+    for p in &history.cells() {
+        if p.x != 0 || p.y != 0 {
+            canvas.set(p, b'#');
+        }
+    }
+    canvas.write(out)
+}
@@ -0,0 +1,150 @@
+use super::Tuple2;
+use intervalset::{Interval, IntervalSet};
+use std::collections::{BTreeMap, HashSet};
+
+/// The lines cells are visited along, each identified by a key and ordered by a coordinate.
+#[derive(Clone, Copy)]
+enum Line {
+    /// Keyed by y, ordered by x
+    Row,
+    /// Keyed by x, ordered by y
+    Column,
+    /// Keyed by x - y, ordered by x
+    Diagonal,
+    /// Keyed by x + y, ordered by x
+    AntiDiagonal,
+}
+
+const LINES: [Line; 4] = [Line::Row, Line::Column, Line::Diagonal, Line::AntiDiagonal];
+
+impl Line {
+    fn of_dir(dir: &Tuple2) -> Self {
+        if dir.y == 0 {
+            Self::Row
+        } else if dir.x == 0 {
+            Self::Column
+        } else if dir.x == dir.y {
+            Self::Diagonal
+        } else {
+            Self::AntiDiagonal
+        }
+    }
+
+    fn key_coord(self, p: &Tuple2) -> (i64, i64) {
+        self.key_coord_xy(p.x as i64, p.y as i64)
+    }
+
+    fn key_coord_xy(self, x: i64, y: i64) -> (i64, i64) {
+        match self {
+            Self::Row => (y, x),
+            Self::Column => (x, y),
+            Self::Diagonal => (x - y, x),
+            Self::AntiDiagonal => (x + y, x),
+        }
+    }
+
+    fn xy(self, key: i64, c: i64) -> (i64, i64) {
+        match self {
+            Self::Row => (c, key),
+            Self::Column => (key, c),
+            Self::Diagonal => (c, c - key),
+            Self::AntiDiagonal => (c, key - c),
+        }
+    }
+
+    fn cell(self, key: i64, c: i64) -> Tuple2 {
+        let (x, y) = self.xy(key, c);
+        Tuple2 {
+            x: x as i32,
+            y: y as i32,
+        }
+    }
+}
+
+/// The cells visited by a knot, stored as runs of cells along the line they were visited on
+/// so a long straight motion costs one insert.
+#[derive(Default)]
+pub struct Visits {
+    lines: [BTreeMap<i64, IntervalSet>; 4],
+}
+
+impl Visits {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, p: Tuple2) {
+        self.insert_run(p, &Tuple2 { x: 1, y: 0 }, 0);
+    }
+
+    /// Adds the cells from `start` to `start + count * dir`.
+    pub fn insert_run(&mut self, start: Tuple2, dir: &Tuple2, count: i32) {
+        let line = Line::of_dir(dir);
+        let end = Tuple2 {
+            x: start.x + dir.x * count,
+            y: start.y + dir.y * count,
+        };
+        let (key, a) = line.key_coord(&start);
+        let (_, b) = line.key_coord(&end);
+        self.lines[line as usize]
+            .entry(key)
+            .or_default()
+            .insert(Interval::new(a, b));
+    }
+
+    /// Returns the number of cells of the run `iv` on the line `key` that are also on a run of
+    /// one of the `earlier` kinds of line. Each line of another kind crosses it at most once.
+    fn count_covered(&self, line: Line, key: i64, iv: &Interval, earlier: &[Line]) -> u64 {
+        let mut covered = Vec::new();
+        for &other in earlier {
+            // the key of the crossing line changes by `slope` with each step along this one
+            let at = |c| {
+                let (x, y) = line.xy(key, c);
+                other.key_coord_xy(x, y)
+            };
+            let base = at(0).0;
+            let slope = at(1).0 - base;
+            let (a, b) = (base + slope * iv.start(), base + slope * iv.end());
+            for (&k, set) in self.lines[other as usize].range(a.min(b)..=a.max(b)) {
+                if (k - base) % slope != 0 {
+                    continue;
+                }
+                let c = (k - base) / slope;
+                if set.contains(at(c).1) {
+                    covered.push(c);
+                }
+            }
+        }
+        covered.sort_unstable();
+        covered.dedup();
+        covered.len() as u64
+    }
+
+    /// Returns the number of distinct cells visited. Each run counts its length less the cells
+    /// it shares with runs on an earlier kind of line.
+    pub fn len(&self) -> u64 {
+        let mut total = 0;
+        for (n, &line) in LINES.iter().enumerate() {
+            for (&key, set) in &self.lines[line as usize] {
+                total += set.len();
+                for iv in set.iter() {
+                    total -= self.count_covered(line, key, iv, &LINES[..n]);
+                }
+            }
+        }
+        total
+    }
+
+    /// Returns every visited cell, expanding the runs.
+    pub fn cells(&self) -> HashSet<Tuple2> {
+        let mut res = HashSet::new();
+        for &line in &LINES {
+            for (&key, set) in &self.lines[line as usize] {
+                for iv in set.iter() {
+                    res.extend((iv.start()..=iv.end()).map(|c| line.cell(key, c)));
+                }
+            }
+        }
+        res
+    }
+}
@@ -0,0 +1,220 @@
+use super::BoxResult;
+use std::collections::HashMap;
+
+/// Index of the `x` register, which starts at 1 and positions the sprite
+pub const X: usize = (b'x' - b'a') as usize;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Op {
+    Noop,
+    Addx,
+    Set,
+    Mul,
+    Jmp,
+    Jnz,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Arg {
+    Reg,
+    Value,
+    Label,
+}
+
+pub struct OpInfo {
+    op: Op,
+    pub name: &'static str,
+    pub cycles: u32,
+    args: &'static [Arg],
+}
+
+/// Instruction set. The effect of an instruction is applied at the end of its last cycle.
+const OPS: [OpInfo; 6] = [
+    OpInfo {
+        op: Op::Noop,
+        name: "noop",
+        cycles: 1,
+        args: &[],
+    },
+    OpInfo {
+        op: Op::Addx,
+        name: "addx",
+        cycles: 2,
+        args: &[Arg::Value],
+    },
+    OpInfo {
+        op: Op::Set,
+        name: "set",
+        cycles: 1,
+        args: &[Arg::Reg, Arg::Value],
+    },
+    OpInfo {
+        op: Op::Mul,
+        name: "mul",
+        cycles: 2,
+        args: &[Arg::Reg, Arg::Value],
+    },
+    OpInfo {
+        op: Op::Jmp,
+        name: "jmp",
+        cycles: 1,
+        args: &[Arg::Label],
+    },
+    OpInfo {
+        op: Op::Jnz,
+        name: "jnz",
+        cycles: 1,
+        args: &[Arg::Value, Arg::Label],
+    },
+];
+
+#[derive(Clone, Copy)]
+enum Operand {
+    Reg(usize),
+    Imm(i64),
+    Target(usize),
+}
+
+pub struct Instr {
+    pub op: &'static OpInfo,
+    args: Vec<Operand>,
+    /// Source of the instruction without labels or comments
+    pub text: String,
+}
+
+/// Assembles a program with one instruction per line. A line may start with a `label:`, and
+/// everything after a `;` is a comment.
+pub fn assemble(src: &str) -> BoxResult<Vec<Instr>> {
+    let mut labels = HashMap::new();
+    let mut lines = Vec::new();
+    for (n, line) in src.lines().enumerate() {
+        let mut line = line.split(';').next().unwrap_or("").trim();
+        if let Some((label, rest)) = line.split_once(':') {
+            let label = label.trim();
+            if label.is_empty() || label.contains(char::is_whitespace) {
+                return Err(format!("Invalid label on line {}", n + 1).into());
+            }
+            if labels.insert(label, lines.len()).is_some() {
+                return Err(format!("Duplicate label {} on line {}", label, n + 1).into());
+            }
+            line = rest.trim();
+        }
+        if !line.is_empty() {
+            lines.push((n + 1, line));
+        }
+    }
+
+    let mut program = Vec::with_capacity(lines.len());
+    for (n, line) in lines {
+        let words = line.split_ascii_whitespace().collect::<Vec<_>>();
+        let op = OPS
+            .iter()
+            .find(|i| i.name == words[0])
+            .ok_or_else(|| format!("Invalid instruction {} on line {}", words[0], n))?;
+        if words.len() - 1 != op.args.len() {
+            return Err(format!("Wrong number of arguments on line {}", n).into());
+        }
+        let args = op
+            .args
+            .iter()
+            .zip(&words[1..])
+            .map(|(&kind, word)| parse_operand(kind, word, &labels))
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| format!("Invalid argument on line {}", n))?;
+        program.push(Instr {
+            op,
+            args,
+            text: words.join(" "),
+        });
+    }
+    Ok(program)
+}
+
+fn parse_operand(kind: Arg, word: &str, labels: &HashMap<&str, usize>) -> Option<Operand> {
+    let reg = match word.as_bytes() {
+        &[c] if c.is_ascii_lowercase() => Some(Operand::Reg((c - b'a') as usize)),
+        _ => None,
+    };
+    match kind {
+        Arg::Reg => reg,
+        Arg::Value => reg.or_else(|| word.parse().ok().map(Operand::Imm)),
+        Arg::Label => labels.get(word).map(|&i| Operand::Target(i)),
+    }
+}
+
+/// Receives the state of the CPU during every cycle.
+pub trait Observer {
+    fn check_cycle(&mut self, cycle: u64, cpu: &Cpu) -> BoxResult<()>;
+}
+
+pub struct Cpu {
+    pub regs: [i64; 26],
+    pub pc: usize,
+    pub cycle: u64,
+}
+
+impl Cpu {
+    pub fn new() -> Self {
+        let mut regs = [0; 26];
+        regs[X] = 1;
+        Self {
+            regs,
+            pc: 0,
+            cycle: 0,
+        }
+    }
+
+    /// Runs a program until the program counter leaves it.
+    pub fn run(&mut self, program: &[Instr], observers: &mut [&mut dyn Observer]) -> BoxResult<()> {
+        while let Some(instr) = program.get(self.pc) {
+            self.exec(instr, observers)?;
+        }
+        Ok(())
+    }
+
+    pub fn exec(&mut self, instr: &Instr, observers: &mut [&mut dyn Observer]) -> BoxResult<()> {
+        for _ in 0..instr.op.cycles {
+            self.check_cycle(observers)?;
+        }
+        let mut next = self.pc + 1;
+        match (instr.op.op, &instr.args[..]) {
+            (Op::Noop, []) => {}
+            (Op::Addx, &[v]) => {
+                self.regs[X] = self.checked(self.regs[X].checked_add(self.value(v)))?
+            }
+            (Op::Set, &[Operand::Reg(r), v]) => self.regs[r] = self.value(v),
+            (Op::Mul, &[Operand::Reg(r), v]) => {
+                self.regs[r] = self.checked(self.regs[r].checked_mul(self.value(v)))?
+            }
+            (Op::Jmp, &[Operand::Target(t)]) => next = t,
+            (Op::Jnz, &[v, Operand::Target(t)]) => {
+                if self.value(v) != 0 {
+                    next = t;
+                }
+            }
+            _ => return Err(format!("Invalid operands at instruction {}", self.pc).into()),
+        }
+        self.pc = next;
+        Ok(())
+    }
+
+    fn check_cycle(&mut self, observers: &mut [&mut dyn Observer]) -> BoxResult<()> {
+        self.cycle += 1;
+        for o in observers.iter_mut() {
+            o.check_cycle(self.cycle, self)?;
+        }
+        Ok(())
+    }
+
+    fn value(&self, v: Operand) -> i64 {
+        match v {
+            Operand::Reg(r) => self.regs[r],
+            Operand::Imm(i) => i,
+            Operand::Target(t) => t as i64,
+        }
+    }
+
+    fn checked(&self, v: Option<i64>) -> BoxResult<i64> {
+        v.ok_or_else(|| format!("Register overflow at instruction {}", self.pc).into())
+    }
+}
@@ -0,0 +1,157 @@
+use super::cpu::{self, Cpu, Instr, Observer};
+use super::{BoxResult, Crt, SignalStrength};
+use std::io::{self, BufRead, Lines, StdinLock, Write};
+use std::process;
+
+const HELP: &str = "Commands: step [<cycles>], run <cycle>, continue, break x, crt, quit";
+
+/// Drives the puzzle observers and, after every cycle, prints a trace line or stops for
+/// debugger commands read from stdin.
+pub struct Inspector<'a> {
+    program: &'a [Instr],
+    strength: &'a mut SignalStrength,
+    crt: &'a mut Crt,
+    trace: bool,
+    debugger: Option<Debugger>,
+}
+
+struct Debugger {
+    input: Lines<StdinLock<'static>>,
+    /// Cycle to stop at, or none to run to the end
+    pause_at: Option<u64>,
+    /// Whether to stop when the `x` register changes
+    break_x: bool,
+    last_x: i64,
+}
+
+impl<'a> Inspector<'a> {
+    pub fn new(
+        program: &'a [Instr],
+        strength: &'a mut SignalStrength,
+        crt: &'a mut Crt,
+        trace: bool,
+        debug: bool,
+    ) -> Self {
+        Self {
+            program,
+            strength,
+            crt,
+            trace,
+            debugger: debug.then(|| Debugger {
+                input: io::stdin().lock().lines(),
+                pause_at: Some(1),
+                break_x: false,
+                last_x: Cpu::new().regs[cpu::X],
+            }),
+        }
+    }
+
+    fn trace_line(&self, cycle: u64, cpu: &Cpu) -> String {
+        let instr = self.program.get(cpu.pc).map_or("", |i| i.text.as_str());
+        let strength = self
+            .strength
+            .sampled
+            .map_or("-".to_string(), |i| i.to_string());
+        let pixel = self.crt.last_pixel().map_or("-".to_string(), |(x, y, c)| {
+            format!("{},{} {}", x, y, c as char)
+        });
+        format!(
+            "{:>6}  {:<14} {:>6} {:>9}  {}",
+            cycle,
+            instr,
+            cpu.regs[cpu::X],
+            strength,
+            pixel
+        )
+    }
+
+    fn write_crt(&self) {
+        let Some((x, y, _)) = self.crt.last_pixel() else {
+            return;
+        };
+        for row in &self.crt.grid[..y] {
+            println!("{}", String::from_utf8_lossy(row));
+        }
+        println!("{}", String::from_utf8_lossy(&self.crt.grid[y][..=x]));
+    }
+
+    /// Reads commands until one resumes execution.
+    fn prompt(&mut self, cycle: u64) -> BoxResult<()> {
+        loop {
+            print!("> ");
+            io::stdout().flush()?;
+            let Some(dbg) = self.debugger.as_mut() else {
+                return Ok(());
+            };
+            let Some(line) = dbg.input.next() else {
+                // run to the end once there are no more commands
+                dbg.pause_at = None;
+                dbg.break_x = false;
+                println!();
+                return Ok(());
+            };
+            let line = line?;
+            match line.split_ascii_whitespace().collect::<Vec<_>>()[..] {
+                [] | ["step" | "s"] => {
+                    dbg.pause_at = Some(cycle + 1);
+                    return Ok(());
+                }
+                ["step" | "s", n] => match n.parse::<u64>() {
+                    Ok(n) if n > 0 => {
+                        dbg.pause_at = Some(cycle + n);
+                        return Ok(());
+                    }
+                    _ => println!("Invalid cycle count"),
+                },
+                ["run" | "r", c] => match c.parse::<u64>() {
+                    Ok(c) if c > cycle => {
+                        dbg.pause_at = Some(c);
+                        return Ok(());
+                    }
+                    _ => println!("Cycle must be after {}", cycle),
+                },
+                ["continue" | "c"] => {
+                    dbg.pause_at = None;
+                    return Ok(());
+                }
+                ["break" | "b", "x"] => {
+                    dbg.break_x = !dbg.break_x;
+                    let state = if dbg.break_x { "on" } else { "off" };
+                    println!("Break on x change {}", state);
+                }
+                ["crt" | "p"] => self.write_crt(),
+                ["quit" | "q"] => process::exit(0),
+                _ => println!("{}", HELP),
+            }
+        }
+    }
+}
+
+impl Observer for Inspector<'_> {
+    fn check_cycle(&mut self, cycle: u64, cpu: &Cpu) -> BoxResult<()> {
+        self.strength.check_cycle(cycle, cpu)?;
+        self.crt.check_cycle(cycle, cpu)?;
+        if self.trace {
+            if cycle == 1 {
+                println!(" cycle  instruction         x  strength  pixel");
+            }
+            println!("{}", self.trace_line(cycle, cpu));
+        }
+        let Some(dbg) = self.debugger.as_mut() else {
+            return Ok(());
+        };
+        let x = cpu.regs[cpu::X];
+        let changed = dbg.break_x && x != dbg.last_x;
+        dbg.last_x = x;
+        if changed || dbg.pause_at.is_some_and(|i| cycle >= i) {
+            if changed {
+                println!("x changed to {}", x);
+            }
+            if !self.trace {
+                println!("{}", self.trace_line(cycle, cpu));
+            }
+            self.prompt(cycle)?;
+        }
+        Ok(())
+    }
+}
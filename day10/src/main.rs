@@ -1,97 +1,201 @@
-use std::fs::File;
-use std::io::prelude::*;
-use std::io::BufReader;
+use cpu::{Cpu, Observer};
+use std::env;
+use std::fs;
+
+mod cpu;
+mod debug;
+mod ocr;
 
 const PUZZLEINPUT: &str = "input.txt";
+const USAGE: &str = "Usage: day10 [-r] [-t] [-d] [-w] [-s <width>x<height>] [-p <sprite width>] [-c <first cycle>,<interval>] [<program>]";
+
+const SCREEN_WIDTH: usize = 40;
+const SCREEN_HEIGHT: usize = 6;
+const SPRITE_WIDTH: usize = 3;
+const SAMPLE_FIRST: u64 = 20;
+const SAMPLE_INTERVAL: u64 = 40;
 
 type BoxError = Box<dyn std::error::Error>;
 type BoxResult<T> = Result<T, BoxError>;
 
 fn main() -> BoxResult<()> {
-    let file = File::open(PUZZLEINPUT)?;
-    let reader = BufReader::new(file);
-
-    let mut vm = VM::new();
-
-    for line in reader.lines() {
-        let line = line?;
-        let (instr, arg) = match line.split_ascii_whitespace().collect::<Vec<_>>()[..] {
-            [instr] => (instr, 0),
-            [instr, arg] => (instr, arg.parse::<i32>()?),
-            _ => return Err("Invalid instruction line".into()),
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    let mut args = args.iter().map(String::as_str).collect::<Vec<_>>();
+    let mut raw = false;
+    let mut trace = false;
+    let mut debug = false;
+    let mut wrap = false;
+    let (mut width, mut height) = (SCREEN_WIDTH, SCREEN_HEIGHT);
+    let mut sprite_width = SPRITE_WIDTH;
+    let (mut first, mut interval) = (SAMPLE_FIRST, SAMPLE_INTERVAL);
+    loop {
+        let n = match args[..] {
+            ["-r", ..] => {
+                raw = true;
+                1
+            }
+            ["-t", ..] => {
+                trace = true;
+                1
+            }
+            ["-d", ..] => {
+                debug = true;
+                1
+            }
+            ["-w", ..] => {
+                wrap = true;
+                1
+            }
+            ["-s", v, ..] => {
+                let (w, h) = v.split_once('x').ok_or(USAGE)?;
+                (width, height) = (w.parse()?, h.parse()?);
+                2
+            }
+            ["-p", v, ..] => {
+                sprite_width = v.parse()?;
+                2
+            }
+            ["-c", v, ..] => {
+                let (f, i) = v.split_once(',').ok_or(USAGE)?;
+                (first, interval) = (f.parse()?, i.parse()?);
+                2
+            }
+            _ => break,
         };
-        vm.exec(instr, arg)?;
+        args.drain(..n);
     }
+    let path = match args[..] {
+        [] => PUZZLEINPUT,
+        [path] => path,
+        _ => return Err(USAGE.into()),
+    };
+    let program = cpu::assemble(&fs::read_to_string(path)?)?;
 
-    println!("Part 1: {}\nPart 2:", vm.strength);
-    for line in vm.grid {
-        println!("{}", String::from_utf8(line)?);
+    let mut strength = SignalStrength::new(first, interval)?;
+    let mut crt = Crt::new(width, height, sprite_width, wrap)?;
+    if trace || debug {
+        let mut inspector = debug::Inspector::new(&program, &mut strength, &mut crt, trace, debug);
+        Cpu::new().run(&program, &mut [&mut inspector])?;
+    } else {
+        Cpu::new().run(&program, &mut [&mut strength, &mut crt])?;
     }
 
+    println!("Part 1: {}", strength.total);
+    let text = ocr::recognize(&crt.grid);
+    if raw || text.is_err() {
+        for line in &crt.grid {
+            println!("{}", String::from_utf8_lossy(line));
+        }
+    }
+    println!("Part 2: {}", text?);
+
     Ok(())
 }
 
-const SCREEN_WIDTH: usize = 40;
-const SCREEN_HEIGHT: usize = 6;
-const SCREEN_SIZE: usize = SCREEN_WIDTH * SCREEN_HEIGHT;
-
-struct VM {
-    cycle: i32,
-    rx: i32,
-    strength: i32,
-    cycle_target: i32,
-    cycle_incr: i32,
-    grid: Vec<Vec<u8>>,
-    scanline: usize,
+/// Sums the cycle number times the `x` register during every sampled cycle.
+struct SignalStrength {
+    total: i64,
+    /// Strength sampled during the most recent cycle
+    sampled: Option<i64>,
+    cycle_target: u64,
+    cycle_incr: u64,
 }
 
-impl VM {
-    fn new() -> Self {
-        Self {
-            cycle: 0,
-            rx: 1,
-            strength: 0,
-            cycle_target: 20,
-            cycle_incr: 40,
-            grid: vec![vec![0; SCREEN_WIDTH]; SCREEN_HEIGHT],
-            scanline: 0,
+impl SignalStrength {
+    fn new(cycle_target: u64, cycle_incr: u64) -> BoxResult<Self> {
+        if cycle_incr == 0 {
+            return Err("Invalid sampling interval".into());
         }
+        Ok(Self {
+            total: 0,
+            sampled: None,
+            cycle_target,
+            cycle_incr,
+        })
     }
+}
 
-    fn exec(&mut self, instr: &str, arg: i32) -> BoxResult<()> {
-        match instr {
-            "noop" => self.exec_noop(),
-            "addx" => self.exec_addx(arg),
-            _ => return Err("Invalid instruction".into()),
+impl Observer for SignalStrength {
+    fn check_cycle(&mut self, cycle: u64, cpu: &Cpu) -> BoxResult<()> {
+        self.sampled = None;
+        if cycle >= self.cycle_target {
+            let strength = (self.cycle_target as i64)
+                .checked_mul(cpu.regs[cpu::X])
+                .ok_or("Signal strength overflow")?;
+            self.total = self
+                .total
+                .checked_add(strength)
+                .ok_or("Signal strength overflow")?;
+            self.sampled = Some(strength);
+            self.cycle_target += self.cycle_incr;
         }
         Ok(())
     }
+}
 
-    fn intersect(&self, x: i32) -> bool {
-        (x - self.rx).abs() <= 1
-    }
+/// Draws one pixel per cycle, lit when the sprite around the `x` register covers it.
+struct Crt {
+    width: usize,
+    sprite_width: usize,
+    /// Whether to start over at the top left pixel after the last one instead of failing
+    wrap: bool,
+    grid: Vec<Vec<u8>>,
+    scanline: usize,
+}
 
-    fn check_cycle(&mut self, cycle: i32, set: bool, next: i32) {
-        self.cycle += cycle;
-        if self.cycle >= self.cycle_target {
-            self.strength += self.cycle_target * self.rx;
-            self.cycle_target += self.cycle_incr;
-        }
-        let y = self.scanline / SCREEN_WIDTH;
-        let x = self.scanline % SCREEN_WIDTH;
-        self.grid[y][x] = if self.intersect(x as i32) { b'#' } else { b'.' };
-        self.scanline = (self.scanline + 1) % SCREEN_SIZE;
-        if set {
-            self.rx = next;
+impl Crt {
+    fn new(width: usize, height: usize, sprite_width: usize, wrap: bool) -> BoxResult<Self> {
+        if width == 0 || height == 0 || sprite_width == 0 {
+            return Err("Invalid screen geometry".into());
         }
+        Ok(Self {
+            width,
+            sprite_width,
+            wrap,
+            grid: vec![vec![b'.'; width]; height],
+            scanline: 0,
+        })
+    }
+
+    /// Returns the position and value of the most recently drawn pixel.
+    fn last_pixel(&self) -> Option<(usize, usize, u8)> {
+        let i = self.scanline.checked_sub(1)?;
+        let (x, y) = (i % self.width, i / self.width);
+        Some((x, y, self.grid[y][x]))
     }
 
-    fn exec_noop(&mut self) {
-        self.check_cycle(1, false, 0)
+    /// Returns whether column `x` is covered by the sprite, which starts `(sprite_width - 1) / 2`
+    /// columns left of `rx`.
+    fn intersect(&self, x: i64, rx: i64) -> bool {
+        let left = rx - (self.sprite_width as i64 - 1) / 2;
+        left <= x && x < left + self.sprite_width as i64
     }
+}
 
-    fn exec_addx(&mut self, arg: i32) {
-        self.check_cycle(1, false, 0);
-        self.check_cycle(1, true, self.rx + arg)
+impl Observer for Crt {
+    fn check_cycle(&mut self, cycle: u64, cpu: &Cpu) -> BoxResult<()> {
+        if self.scanline == self.width * self.grid.len() {
+            if !self.wrap {
+                return Err(
+                    format!("Program ran past the end of the frame at cycle {}", cycle).into(),
+                );
+            }
+            if cycle == self.scanline as u64 + 1 {
+                eprintln!(
+                    "Warning: program ran past the end of the frame at cycle {}",
+                    cycle
+                );
+            }
+            self.scanline = 0;
+        }
+        let y = self.scanline / self.width;
+        let x = self.scanline % self.width;
+        self.grid[y][x] = if self.intersect(x as i64, cpu.regs[cpu::X]) {
+            b'#'
+        } else {
+            b'.'
+        };
+        self.scanline += 1;
+        Ok(())
     }
 }
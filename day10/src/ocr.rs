@@ -0,0 +1,101 @@
+use super::BoxResult;
+
+pub const GLYPH_WIDTH: usize = 4;
+pub const GLYPH_HEIGHT: usize = 6;
+/// Glyphs are separated by one blank column
+const GLYPH_PITCH: usize = GLYPH_WIDTH + 1;
+
+/// The letters known to fit a four column cell. `Y` is five columns wide, so it is left out.
+const GLYPHS: [(char, [&str; GLYPH_HEIGHT]); 17] = [
+    ('A', [".##.", "#..#", "#..#", "####", "#..#", "#..#"]),
+    ('B', ["###.", "#..#", "###.", "#..#", "#..#", "###."]),
+    ('C', [".##.", "#..#", "#...", "#...", "#..#", ".##."]),
+    ('E', ["####", "#...", "###.", "#...", "#...", "####"]),
+    ('F', ["####", "#...", "###.", "#...", "#...", "#..."]),
+    ('G', [".##.", "#..#", "#...", "#.##", "#..#", ".###"]),
+    ('H', ["#..#", "#..#", "####", "#..#", "#..#", "#..#"]),
+    ('I', [".###", "..#.", "..#.", "..#.", "..#.", ".###"]),
+    ('J', ["..##", "...#", "...#", "...#", "#..#", ".##."]),
+    ('K', ["#..#", "#.#.", "##..", "#.#.", "#.#.", "#..#"]),
+    ('L', ["#...", "#...", "#...", "#...", "#...", "####"]),
+    ('O', [".##.", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('P', ["###.", "#..#", "#..#", "###.", "#...", "#..."]),
+    ('R', ["###.", "#..#", "#..#", "###.", "#.#.", "#..#"]),
+    ('S', [".###", "#...", "#...", ".##.", "...#", "###."]),
+    ('U', ["#..#", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('Z', ["####", "...#", "..#.", ".#..", "#...", "####"]),
+];
+
+/// Reads the letters drawn with `#` on a screen, one line of text per six rows. Blank cells
+/// are read as spaces.
+pub fn recognize(grid: &[Vec<u8>]) -> BoxResult<String> {
+    if !grid.len().is_multiple_of(GLYPH_HEIGHT) {
+        return Err(format!("Screen height is not a multiple of {}", GLYPH_HEIGHT).into());
+    }
+    let mut lines = Vec::new();
+    for (n, rows) in grid.chunks(GLYPH_HEIGHT).enumerate() {
+        let width = rows[0].len();
+        let mut line = String::new();
+        for x in (0..width).step_by(GLYPH_PITCH) {
+            let cell = rows
+                .iter()
+                .map(|row| row.get(x..x + GLYPH_WIDTH))
+                .collect::<Option<Vec<_>>>()
+                .ok_or_else(|| format!("Partial glyph at column {}", x))?;
+            let gap_lit =
+                x + GLYPH_WIDTH < width && rows.iter().any(|row| row[x + GLYPH_WIDTH] == b'#');
+            let c = if gap_lit {
+                None
+            } else if cell.iter().all(|row| !row.contains(&b'#')) {
+                Some(' ')
+            } else {
+                GLYPHS
+                    .iter()
+                    .find(|(_, glyph)| glyph.iter().zip(&cell).all(|(a, b)| a.as_bytes() == *b))
+                    .map(|&(c, _)| c)
+            };
+            let c = c.ok_or_else(|| match n {
+                0 => format!("Unknown glyph at column {}", x),
+                _ => format!("Unknown glyph at column {} of line {}", x, n + 1),
+            })?;
+            line.push(c);
+        }
+        lines.push(line.trim_end().to_string());
+    }
+    Ok(lines.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Draws glyphs side by side, each followed by a blank column.
+    fn draw(glyphs: &[[&str; GLYPH_HEIGHT]]) -> Vec<Vec<u8>> {
+        (0..GLYPH_HEIGHT)
+            .map(|y| {
+                glyphs
+                    .iter()
+                    .flat_map(|g| g[y].bytes().chain([b'.']))
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn recognizes_every_glyph() {
+        for (c, glyph) in &GLYPHS {
+            assert_eq!(recognize(&draw(&[*glyph])).unwrap(), c.to_string());
+        }
+        let all = GLYPHS.iter().map(|&(_, g)| g).collect::<Vec<_>>();
+        let text = GLYPHS.iter().map(|&(c, _)| c).collect::<String>();
+        assert_eq!(recognize(&draw(&all)).unwrap(), text);
+    }
+
+    #[test]
+    fn reports_unknown_glyph_column() {
+        let unknown = ["#..#", ".##.", "....", "....", "....", "...."];
+        let grid = draw(&[GLYPHS[0].1, unknown]);
+        let err = recognize(&grid).unwrap_err().to_string();
+        assert_eq!(err, format!("Unknown glyph at column {}", GLYPH_PITCH));
+    }
+}
@@ -1,3 +1,4 @@
+use intervalset::{Interval, IntervalSet};
 use regex::Regex;
 use std::collections::HashSet;
 use std::fs::File;
@@ -19,7 +20,6 @@ fn main() -> BoxResult<()> {
 
     let mut sensors = Vec::new();
     let mut beacons = HashSet::new();
-    let mut bounds = None;
 
     for line in reader.lines() {
         let line = line?;
@@ -34,75 +34,43 @@ fn main() -> BoxResult<()> {
         );
         beacons.insert(beacon);
         let radius = pos.manhattan_distance(&beacon);
-        let sensor = Sensor::new(pos, radius);
-        if let Some((x1, x2)) = sensor.bounds_x(PUZZLE_ROW) {
-            match &mut bounds {
-                None => bounds = Some((x1, x2)),
-                Some((v1, v2)) => {
-                    if x1 < *v1 {
-                        *v1 = x1
-                    }
-                    if x2 > *v2 {
-                        *v2 = x2
-                    }
-                }
-            }
-        }
-        sensors.push(sensor);
+        sensors.push(Sensor::new(pos, radius));
     }
 
     {
-        let mut count = 0;
-        if let Some((left_bound, right_bound)) = bounds {
-            let mut x = left_bound;
-            while x <= right_bound {
-                let pos = Pos::new(x, PUZZLE_ROW);
-                if beacons.contains(&pos) {
-                    x += 1;
-                    continue;
-                }
-                for i in &sensors {
-                    if !i.in_range(&pos) {
-                        continue;
-                    }
-                    let (_, x2) = i.bounds_x(PUZZLE_ROW).ok_or("Invariant violated")?;
-                    if x2 == x {
-                        count += 1;
-                    } else {
-                        count += x2 - x;
-                        x = x2 - 1;
-                    }
-                    break;
-                }
-                x += 1;
-            }
-        }
-        println!("Part 1: {}", count);
+        let covered = covered_row(&sensors, PUZZLE_ROW);
+        let row_beacons = beacons
+            .iter()
+            .filter(|i| i.y == PUZZLE_ROW)
+            .map(|i| Interval::new(i.x as i64, i.x as i64))
+            .collect();
+        println!("Part 1: {}", covered.difference(&row_beacons).len());
     }
     {
-        let mut y = 0;
-        'outer2: while y <= PUZZLE_BOUND {
-            let mut x = 0;
-            'outer: while x <= PUZZLE_BOUND {
-                let pos = Pos::new(x, y);
-                for i in &sensors {
-                    if !i.in_range(&pos) {
-                        continue;
-                    }
-                    let (_, x2) = i.bounds_x(y).ok_or("Invariant violated")?;
-                    x = x2 + 1;
-                    continue 'outer;
-                }
-                println!("Part 2: {}", x as i64 * PUZZLE_BOUND as i64 + y as i64);
-                break 'outer2;
+        let bounds = IntervalSet::from(Interval::new(0, PUZZLE_BOUND as i64));
+        for y in 0..=PUZZLE_BOUND {
+            let covered = covered_row(&sensors, y);
+            if covered.is_superset(&bounds) {
+                continue;
             }
-            y += 1
+            let x = bounds
+                .difference(&covered)
+                .iter()
+                .next()
+                .ok_or("Invariant violated")?
+                .start();
+            println!("Part 2: {}", x * PUZZLE_BOUND as i64 + y as i64);
+            break;
         }
     }
 
     Ok(())
 }
 
+fn covered_row(sensors: &[Sensor], y: i32) -> IntervalSet {
+    sensors.iter().filter_map(|i| i.bounds_x(y)).collect()
+}
+
 #[derive(Clone, Copy, PartialEq, Eq, Hash)]
 struct Pos {
     y: i32,
@@ -129,17 +97,16 @@ impl Sensor {
         Self { pos, radius }
     }
 
-    fn in_range(&self, pos: &Pos) -> bool {
-        self.pos.manhattan_distance(&pos) <= self.radius
-    }
-
-    fn bounds_x(&self, y: i32) -> Option<(i32, i32)> {
+    fn bounds_x(&self, y: i32) -> Option<Interval> {
         let vdelta = (self.pos.y - y).abs();
         if vdelta > self.radius {
             None
         } else {
             let delta = self.radius - vdelta;
-            Some((self.pos.x - delta, self.pos.x + delta))
+            Some(Interval::new(
+                (self.pos.x - delta) as i64,
+                (self.pos.x + delta) as i64,
+            ))
         }
     }
 }
@@ -0,0 +1,341 @@
+use std::cmp::{max, min};
+
+/// A closed interval of integers from `start` to `end` inclusive, which is never empty.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Interval {
+    start: i64,
+    end: i64,
+}
+
+impl Interval {
+    /// Creates an interval from two endpoints given in any order.
+    pub fn new(x: i64, y: i64) -> Self {
+        if x <= y {
+            Self { start: x, end: y }
+        } else {
+            Self { start: y, end: x }
+        }
+    }
+
+    pub fn start(&self) -> i64 {
+        self.start
+    }
+
+    pub fn end(&self) -> i64 {
+        self.end
+    }
+
+    /// Returns the number of integers in the interval, saturating at `u64::MAX`.
+    pub fn width(&self) -> u64 {
+        self.end.abs_diff(self.start).saturating_add(1)
+    }
+
+    pub fn contains(&self, x: i64) -> bool {
+        self.start <= x && x <= self.end
+    }
+}
+
+/// A set of integers stored as sorted, disjoint, and non-adjacent closed intervals.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct IntervalSet {
+    intervals: Vec<Interval>,
+}
+
+impl IntervalSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.intervals.is_empty()
+    }
+
+    /// Returns the number of integers covered by the set, saturating at `u64::MAX`.
+    pub fn len(&self) -> u64 {
+        self.intervals
+            .iter()
+            .fold(0, |acc, i| acc.saturating_add(i.width()))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Interval> {
+        self.intervals.iter()
+    }
+
+    /// Returns the uncovered intervals between the first and last covered integers.
+    pub fn gaps(&self) -> impl Iterator<Item = Interval> + '_ {
+        self.intervals.windows(2).map(|w| Interval {
+            start: w[0].end + 1,
+            end: w[1].start - 1,
+        })
+    }
+
+    /// Adds an interval to the set, merging it with any overlapping or adjacent intervals.
+    pub fn insert(&mut self, iv: Interval) {
+        let lo = self
+            .intervals
+            .partition_point(|i| i.end < iv.start.saturating_sub(1));
+        let hi = self
+            .intervals
+            .partition_point(|i| i.start <= iv.end.saturating_add(1));
+        let mut merged = iv;
+        if lo < hi {
+            merged.start = min(merged.start, self.intervals[lo].start);
+            merged.end = max(merged.end, self.intervals[hi - 1].end);
+        }
+        self.intervals.splice(lo..hi, std::iter::once(merged));
+    }
+
+    /// Adds every interval of another set to this set.
+    pub fn merge(&mut self, other: &Self) {
+        for &i in &other.intervals {
+            self.insert(i);
+        }
+    }
+
+    pub fn contains(&self, x: i64) -> bool {
+        let idx = self.intervals.partition_point(|i| i.end < x);
+        idx < self.intervals.len() && self.intervals[idx].start <= x
+    }
+
+    pub fn contains_interval(&self, iv: &Interval) -> bool {
+        let idx = self.intervals.partition_point(|i| i.end < iv.start);
+        idx < self.intervals.len()
+            && self.intervals[idx].start <= iv.start
+            && iv.end <= self.intervals[idx].end
+    }
+
+    pub fn is_superset(&self, other: &Self) -> bool {
+        other.intervals.iter().all(|i| self.contains_interval(i))
+    }
+
+    pub fn is_disjoint(&self, other: &Self) -> bool {
+        self.intersection(other).is_empty()
+    }
+
+    pub fn union(&self, other: &Self) -> Self {
+        let mut res = Self::new();
+        let (mut a, mut b) = (
+            self.intervals.iter().peekable(),
+            other.intervals.iter().peekable(),
+        );
+        loop {
+            let next = match (a.peek(), b.peek()) {
+                (Some(x), Some(y)) => {
+                    if x.start <= y.start {
+                        a.next()
+                    } else {
+                        b.next()
+                    }
+                }
+                (Some(_), None) => a.next(),
+                (None, Some(_)) => b.next(),
+                (None, None) => break,
+            };
+            if let Some(&i) = next {
+                res.push_back(i);
+            }
+        }
+        res
+    }
+
+    pub fn intersection(&self, other: &Self) -> Self {
+        let mut res = Self::new();
+        let (a, b) = (&self.intervals, &other.intervals);
+        let (mut i, mut j) = (0, 0);
+        while i < a.len() && j < b.len() {
+            let start = max(a[i].start, b[j].start);
+            let end = min(a[i].end, b[j].end);
+            if start <= end {
+                res.push_back(Interval { start, end });
+            }
+            if a[i].end < b[j].end {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+        res
+    }
+
+    /// Returns the integers in this set that are not in the other set.
+    pub fn difference(&self, other: &Self) -> Self {
+        let mut res = Self::new();
+        let b = &other.intervals;
+        let mut j = 0;
+        for iv in &self.intervals {
+            while j < b.len() && b[j].end < iv.start {
+                j += 1;
+            }
+            let mut rest = Some(iv.start);
+            let mut k = j;
+            while let Some(start) = rest {
+                if k >= b.len() || b[k].start > iv.end {
+                    break;
+                }
+                if b[k].start > start {
+                    res.push_back(Interval {
+                        start,
+                        end: b[k].start - 1,
+                    });
+                }
+                rest = if b[k].end >= iv.end {
+                    None
+                } else {
+                    Some(b[k].end + 1)
+                };
+                k += 1;
+            }
+            if let Some(start) = rest {
+                res.push_back(Interval { start, end: iv.end });
+            }
+        }
+        res
+    }
+
+    /// Appends an interval that starts no earlier than every interval already in the set.
+    fn push_back(&mut self, iv: Interval) {
+        if let Some(last) = self.intervals.last_mut() {
+            if iv.start <= last.end.saturating_add(1) {
+                last.end = max(last.end, iv.end);
+                return;
+            }
+        }
+        self.intervals.push(iv);
+    }
+}
+
+impl From<Interval> for IntervalSet {
+    fn from(iv: Interval) -> Self {
+        Self {
+            intervals: vec![iv],
+        }
+    }
+}
+
+impl FromIterator<Interval> for IntervalSet {
+    fn from_iter<I: IntoIterator<Item = Interval>>(iter: I) -> Self {
+        let mut res = Self::new();
+        for i in iter {
+            res.insert(i);
+        }
+        res
+    }
+}
+
+impl Extend<Interval> for IntervalSet {
+    fn extend<I: IntoIterator<Item = Interval>>(&mut self, iter: I) {
+        for i in iter {
+            self.insert(i);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set(intervals: &[(i64, i64)]) -> IntervalSet {
+        intervals
+            .iter()
+            .map(|&(x, y)| Interval::new(x, y))
+            .collect()
+    }
+
+    fn bounds(s: &IntervalSet) -> Vec<(i64, i64)> {
+        s.iter().map(|i| (i.start(), i.end())).collect()
+    }
+
+    #[test]
+    fn new_orders_endpoints() {
+        let iv = Interval::new(5, 3);
+        assert_eq!((iv.start(), iv.end()), (3, 5));
+        assert_eq!(iv.width(), 3);
+        assert_eq!(Interval::new(4, 4).width(), 1);
+    }
+
+    #[test]
+    fn insert_merges_adjacent_and_overlapping() {
+        assert_eq!(bounds(&set(&[(1, 3), (4, 6)])), [(1, 6)]);
+        assert_eq!(bounds(&set(&[(1, 3), (5, 6)])), [(1, 3), (5, 6)]);
+        assert_eq!(bounds(&set(&[(1, 3), (5, 6), (8, 9), (2, 8)])), [(1, 9)]);
+        assert_eq!(bounds(&set(&[(5, 6), (1, 2), (3, 3)])), [(1, 3), (5, 6)]);
+    }
+
+    #[test]
+    fn containment() {
+        let s = set(&[(1, 3), (7, 9)]);
+        assert!(s.contains(1) && s.contains(3) && s.contains(8));
+        assert!(!s.contains(0) && !s.contains(5) && !s.contains(10));
+        assert!(s.contains_interval(&Interval::new(7, 9)));
+        assert!(!s.contains_interval(&Interval::new(3, 7)));
+        assert!(s.is_superset(&set(&[(2, 3), (8, 8)])));
+        assert!(!s.is_superset(&set(&[(2, 4)])));
+        assert!(s.is_superset(&IntervalSet::new()));
+    }
+
+    #[test]
+    fn difference_splits_intervals() {
+        assert_eq!(
+            bounds(&set(&[(1, 10)]).difference(&set(&[(4, 6)]))),
+            [(1, 3), (7, 10)]
+        );
+        assert_eq!(
+            bounds(&set(&[(1, 10)]).difference(&set(&[(0, 1), (3, 3), (10, 12)]))),
+            [(2, 2), (4, 9)]
+        );
+        assert!(set(&[(1, 10)]).difference(&set(&[(0, 11)])).is_empty());
+        assert_eq!(
+            bounds(&set(&[(1, 3)]).difference(&set(&[(5, 6)]))),
+            [(1, 3)]
+        );
+    }
+
+    #[test]
+    fn union_and_intersection() {
+        let a = set(&[(1, 3), (10, 12)]);
+        let b = set(&[(4, 5), (11, 20)]);
+        assert_eq!(bounds(&a.union(&b)), [(1, 5), (10, 20)]);
+        assert_eq!(bounds(&a.intersection(&b)), [(11, 12)]);
+        assert!(a.is_disjoint(&set(&[(4, 9)])));
+        assert!(!a.is_disjoint(&b));
+    }
+
+    #[test]
+    fn empty_sets() {
+        let empty = IntervalSet::new();
+        let a = set(&[(1, 3)]);
+        assert!(empty.is_empty());
+        assert_eq!(empty.len(), 0);
+        assert_eq!(empty.gaps().count(), 0);
+        assert_eq!(a.union(&empty), a);
+        assert_eq!(empty.union(&a), a);
+        assert!(a.intersection(&empty).is_empty());
+        assert_eq!(a.difference(&empty), a);
+        assert!(empty.difference(&a).is_empty());
+    }
+
+    #[test]
+    fn i64_extremes() {
+        let all = set(&[(i64::MIN, i64::MAX)]);
+        assert_eq!(all.len(), u64::MAX);
+        assert_eq!(
+            bounds(&set(&[(i64::MIN, -1), (0, i64::MAX)])),
+            [(i64::MIN, i64::MAX)]
+        );
+        assert_eq!(
+            bounds(&all.difference(&set(&[(0, 0)]))),
+            [(i64::MIN, -1), (1, i64::MAX)]
+        );
+        assert_eq!(
+            bounds(&all.difference(&set(&[(i64::MIN, i64::MIN), (i64::MAX, i64::MAX)]))),
+            [(i64::MIN + 1, i64::MAX - 1)]
+        );
+        let ends = set(&[(i64::MIN, i64::MIN), (i64::MAX, i64::MAX)]);
+        assert_eq!(ends.len(), 2);
+        assert_eq!(bounds(&ends.intersection(&all)), bounds(&ends));
+        assert_eq!(
+            ends.gaps().collect::<Vec<_>>(),
+            [Interval::new(i64::MIN + 1, i64::MAX - 1)]
+        );
+    }
+}